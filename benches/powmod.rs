@@ -0,0 +1,48 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use encoder::{montgomery::MontgomeryContext, primes};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{one, zero};
+
+/// The recursive square-and-multiply `primes::powmod` used before the
+/// Montgomery engine, kept here as the baseline.
+fn recursive_powmod(b: &BigUint, e: &BigUint, m: &BigUint, r: BigUint) -> BigUint {
+    if *e == zero() {
+        return r;
+    }
+    if e & one::<BigUint>() == one() {
+        recursive_powmod(&((b * b) % m), &(e >> 1), m, (r * b) % m)
+    } else {
+        recursive_powmod(&((b * b) % m), &(e >> 1), m, r)
+    }
+}
+
+fn powmod_benchmark(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let mut group = c.benchmark_group("powmod");
+    group.sample_size(10);
+    for &bits in [1024u64, 2048, 4096].iter() {
+        let m = rng.gen_biguint(bits) | one::<BigUint>();
+        let b = rng.gen_biguint_below(&m);
+        let e = rng.gen_biguint_below(&m);
+        let context = MontgomeryContext::new(&m).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("recursive", bits), &bits, |bench, _| {
+            bench.iter(|| recursive_powmod(&b, &e, &m, one()))
+        });
+        group.bench_with_input(BenchmarkId::new("powmod", bits), &bits, |bench, _| {
+            bench.iter(|| primes::powmod(&b, &e, &m))
+        });
+        group.bench_with_input(BenchmarkId::new("montgomery", bits), &bits, |bench, _| {
+            bench.iter(|| context.pow(&b, &e))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("montgomery_ct", bits),
+            &bits,
+            |bench, _| bench.iter(|| context.pow_ct(&b, &e)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, powmod_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,26 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use encoder::keys::{self, DEFAULT_PUBLIC_EXPONENT};
+use num_bigint::RandBigInt;
+
+fn decrypt_benchmark(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let mut group = c.benchmark_group("decrypt_raw");
+    group.sample_size(10);
+    for &bits in [1024u64, 2048].iter() {
+        let (public_key, mut private_key) =
+            keys::get_keys(4, bits / 2, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+        let ciphertext = rng.gen_biguint_below(public_key.n());
+
+        group.bench_with_input(BenchmarkId::new("blinded", bits), &bits, |bench, _| {
+            bench.iter(|| private_key.decrypt_raw(&ciphertext).unwrap())
+        });
+        private_key.set_blinding(false);
+        group.bench_with_input(BenchmarkId::new("unblinded", bits), &bits, |bench, _| {
+            bench.iter(|| private_key.decrypt_raw(&ciphertext).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decrypt_benchmark);
+criterion_main!(benches);
@@ -1,12 +1,19 @@
+// Kept as written before the library split; only the `primes` calls follow the move.
+#![allow(
+    clippy::needless_borrow,
+    clippy::needless_range_loop,
+    clippy::question_mark,
+    clippy::explicit_counter_loop
+)]
+
 use std::{
     collections::HashSet,
     fs::{read, read_to_string},
 };
 
+use encoder::primes;
 use num_bigint::{BigUint, ToBigUint};
-use num_traits::{one, FromPrimitive, Num, ToPrimitive};
-
-mod primes;
+use num_traits::{one, Num, ToPrimitive};
 
 fn generate_possible_keys(p: &BigUint, q: &BigUint) -> Vec<(BigUint, BigUint)> {
     let one = &one::<BigUint>();
@@ -26,7 +33,7 @@ fn generate_possible_keys(p: &BigUint, q: &BigUint) -> Vec<(BigUint, BigUint)> {
 
     let mut keys = vec![];
     for d in ds {
-        let e = primes::mul_inv_mod(d.clone(), f.clone());
+        let e = primes::mul_inv_mod(d.clone(), f.clone()).unwrap();
         // println!("e={},n={}",e,n);
         keys.push((e, n.clone()));
     }
@@ -89,7 +96,7 @@ fn try_decode(private_key_e_n: (BigUint, BigUint), encoded_msg: &[BigUint]) -> O
     let (e, n) = private_key_e_n;
     let mut msg = vec![];
     for byte in encoded_msg {
-        let rem = primes::powmod(byte.clone(), e.clone(), n.clone());
+        let rem = primes::powmod(byte, &e, &n);
         msg.push(rem);
     }
     for i in msg.iter() {
@@ -0,0 +1,210 @@
+use num_bigint::BigUint;
+use num_traits::Num;
+
+use crate::error::{Error, Result};
+use crate::hash::Hash;
+use crate::keys::{RsaPrivateKey, RsaPublicKey};
+use crate::{oaep, pkcs1v15};
+
+/// Padding scheme applied to every block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Padding {
+    Pkcs1v15,
+    Oaep { hash: Hash, label: Vec<u8> },
+}
+
+impl Padding {
+    /// Bytes of every block reserved for padding.
+    pub fn overhead(&self) -> usize {
+        match self {
+            Padding::Pkcs1v15 => pkcs1v15::OVERHEAD,
+            Padding::Oaep { hash, .. } => oaep::overhead(*hash),
+        }
+    }
+
+    /// Largest message block that fits into one integer below a `key_size`-byte modulus.
+    pub fn max_block_len(&self, key_size: usize) -> usize {
+        key_size.saturating_sub(self.overhead())
+    }
+
+    fn encrypt(&self, public_key: &RsaPublicKey, block: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Padding::Pkcs1v15 => pkcs1v15::encrypt(public_key, block),
+            Padding::Oaep { hash, label } => oaep::encrypt(public_key, *hash, label, block),
+        }
+    }
+
+    fn decrypt(&self, private_key: &RsaPrivateKey, block: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Padding::Pkcs1v15 => pkcs1v15::decrypt(private_key, block),
+            Padding::Oaep { hash, label } => oaep::decrypt(private_key, *hash, label, block),
+        }
+    }
+}
+
+/// I2OSP: big-endian encoding of `x` left-padded with zeros to `len` bytes.
+/// `x` must fit into `len` bytes.
+pub fn i2osp(x: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = x.to_bytes_be();
+    let mut out = vec![0; len - bytes.len()];
+    out.extend_from_slice(&bytes);
+    out
+}
+
+/// OS2IP: the integer with big-endian encoding `bytes`.
+pub fn os2ip(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(bytes)
+}
+
+/// Splits `msg` into blocks of `max_block_len` bytes and encrypts each block
+/// with `padding`.
+pub fn encode(public_key: &RsaPublicKey, padding: &Padding, msg: &[u8]) -> Result<Vec<BigUint>> {
+    let block_len = padding.max_block_len(public_key.size());
+    if block_len == 0 {
+        return Err(Error::MessageTooLong);
+    }
+    msg.chunks(block_len)
+        .map(|block| Ok(os2ip(&padding.encrypt(public_key, block)?)))
+        .collect()
+}
+
+/// Decrypts the blocks produced by `encode` and joins them back together.
+/// With PKCS#1 v1.5 a block with invalid padding decrypts to garbage instead
+/// of failing, see `pkcs1v15::decrypt`.
+pub fn decode(
+    private_key: &RsaPrivateKey,
+    padding: &Padding,
+    encoded_msg: &[BigUint],
+) -> Result<Vec<u8>> {
+    let mut msg = vec![];
+    for (offset, block) in encoded_msg.iter().enumerate() {
+        if block >= private_key.n() {
+            return Err(Error::MalformedCiphertext { offset });
+        }
+        let ciphertext = i2osp(block, private_key.size());
+        msg.extend(
+            padding
+                .decrypt(private_key, &ciphertext)
+                .map_err(|_| Error::MalformedCiphertext { offset })?,
+        );
+    }
+    Ok(msg)
+}
+
+/// Ciphertext file layout: space-separated decimal numbers.
+pub fn write_ciphertext(encoded_msg: &[BigUint]) -> Vec<u8> {
+    let mut file = vec![];
+    for val in encoded_msg {
+        file.extend(val.to_string().into_bytes());
+        file.extend(b" ");
+    }
+    file
+}
+
+pub fn read_ciphertext(file: &[u8]) -> Result<Vec<BigUint>> {
+    String::from_utf8_lossy(file)
+        .split_whitespace()
+        .enumerate()
+        .map(|(offset, x)| {
+            BigUint::from_str_radix(x, 10).map_err(|_| Error::MalformedCiphertext { offset })
+        })
+        .collect()
+}
+
+#[test]
+fn encode_decode_test() {
+    let (public_key, private_key) =
+        crate::keys::get_keys(2, 64, &crate::keys::DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let msg = b"Hello, world!";
+    let encoded = encode(&public_key, &Padding::Pkcs1v15, msg).unwrap();
+    let encoded = read_ciphertext(&write_ciphertext(&encoded)).unwrap();
+    assert_eq!(
+        decode(&private_key, &Padding::Pkcs1v15, &encoded).unwrap(),
+        msg
+    );
+}
+
+#[test]
+fn blocks_test() {
+    use num_bigint::ToBigUint;
+    let (public_key, private_key) =
+        crate::keys::get_keys(2, 64, &crate::keys::DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let padding = Padding::Pkcs1v15;
+    let block_len = padding.max_block_len(public_key.size());
+    assert_eq!(block_len, 16 - 11);
+    for &len in [
+        0,
+        1,
+        block_len - 1,
+        block_len,
+        block_len + 1,
+        10 * block_len,
+    ]
+    .iter()
+    {
+        let msg: Vec<u8> = (0..len).map(|i| (i % 3) as u8).collect();
+        let encoded = encode(&public_key, &padding, &msg).unwrap();
+        assert_eq!(encoded.len(), len.div_ceil(block_len));
+        assert_eq!(decode(&private_key, &padding, &encoded).unwrap(), msg);
+    }
+    let encoded = encode(&public_key, &padding, b"eeeee").unwrap();
+    assert_eq!(encoded.len(), 1);
+    let unpadded = public_key.encrypt_raw(&2.to_biguint().unwrap()).unwrap();
+    let decoded = decode(&private_key, &padding, &[encoded[0].clone(), unpadded]).unwrap();
+    assert_eq!(&decoded[..5], b"eeeee");
+    assert!(decoded.len() <= 5 + block_len);
+}
+
+#[test]
+fn oaep_blocks_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let public_key = private_key.to_public_key();
+    let padding = Padding::Oaep {
+        hash: Hash::Sha256,
+        label: b"blocks".to_vec(),
+    };
+    let block_len = padding.max_block_len(public_key.size());
+    assert_eq!(block_len, 128 - 66);
+    let msg: Vec<u8> = (0..3 * block_len + 1).map(|i| i as u8).collect();
+    let encoded = encode(&public_key, &padding, &msg).unwrap();
+    assert_eq!(encoded.len(), 4);
+    assert_eq!(decode(&private_key, &padding, &encoded).unwrap(), msg);
+    assert!(matches!(
+        decode(&private_key, &Padding::Pkcs1v15, &encoded),
+        Ok(ref garbage) if garbage != &msg
+    ));
+    let sha512 = Padding::Oaep {
+        hash: Hash::Sha512,
+        label: vec![],
+    };
+    assert!(matches!(
+        encode(&public_key, &sha512, b"x"),
+        Err(Error::MessageTooLong)
+    ));
+}
+
+#[test]
+fn small_key_test() {
+    let (public_key, _) = crate::keys::get_keys(2, 40, &3u32.into()).unwrap();
+    assert_eq!(public_key.size(), 10);
+    assert!(matches!(
+        encode(&public_key, &Padding::Pkcs1v15, b"x"),
+        Err(Error::MessageTooLong)
+    ));
+}
+
+#[test]
+fn malformed_ciphertext_test() {
+    use num_bigint::ToBigUint;
+    let (_, private_key) =
+        crate::keys::get_keys(2, 64, &crate::keys::DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    assert!(matches!(
+        read_ciphertext(b"12 34 5x6 78"),
+        Err(Error::MalformedCiphertext { offset: 2 })
+    ));
+    let too_big = vec![1u32.to_biguint().unwrap(), private_key.n().clone()];
+    assert!(matches!(
+        decode(&private_key, &Padding::Pkcs1v15, &too_big),
+        Err(Error::MalformedCiphertext { offset: 1 })
+    ));
+}
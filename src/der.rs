@@ -0,0 +1,410 @@
+//! DER encoding of `RSAPublicKey` and `RSAPrivateKey` (RFC 8017 Appendix A.1):
+//!
+//! ```text
+//! RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+//! RSAPrivateKey ::= SEQUENCE {
+//!     version Version, modulus INTEGER, publicExponent INTEGER,
+//!     privateExponent INTEGER, prime1 INTEGER, prime2 INTEGER,
+//!     exponent1 INTEGER, exponent2 INTEGER, coefficient INTEGER,
+//!     otherPrimeInfos OtherPrimeInfos OPTIONAL }
+//! ```
+//!
+//! PKCS#8 `PrivateKeyInfo` (RFC 5208) and `SubjectPublicKeyInfo` (RFC 5280)
+//! wrap them with the `rsaEncryption` algorithm identifier:
+//!
+//! ```text
+//! PrivateKeyInfo ::= SEQUENCE {
+//!     version INTEGER, privateKeyAlgorithm AlgorithmIdentifier,
+//!     privateKey OCTET STRING -- RSAPrivateKey -- }
+//! SubjectPublicKeyInfo ::= SEQUENCE {
+//!     algorithm AlgorithmIdentifier, subjectPublicKey BIT STRING -- RSAPublicKey -- }
+//! ```
+//!
+//! Only two-prime keys (version 0) are supported. Decoding is strict: lengths
+//! and integers must be minimal, integers non-negative, and nothing may follow
+//! the outer `SEQUENCE`.
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+use crate::error::{Error, Result};
+use crate::keys::{RsaPrivateKey, RsaPublicKey};
+
+pub(crate) const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const NULL: u8 = 0x05;
+pub(crate) const OBJECT_IDENTIFIER: u8 = 0x06;
+pub(crate) const SEQUENCE: u8 = 0x30;
+
+/// `rsaEncryption`, 1.2.840.113549.1.1.1.
+const RSA_ENCRYPTION: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+/// Versions of a two-prime and a multi-prime `RSAPrivateKey`.
+const TWO_PRIME: u32 = 0;
+const MULTI_PRIME: u32 = 1;
+
+/// Appends the DER length octets of `len`.
+fn push_len(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+        return;
+    }
+    let bytes = len.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+    out.push(0x80 | (bytes.len() - skip) as u8);
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+pub(crate) fn push_tlv(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    push_len(out, value.len());
+    out.extend_from_slice(value);
+}
+
+/// Non-negative `INTEGER`: minimal big-endian, with a leading zero if the
+/// top bit is set.
+pub(crate) fn push_integer(out: &mut Vec<u8>, x: &BigUint) {
+    let mut bytes = x.to_bytes_be();
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    push_tlv(out, INTEGER, &bytes);
+}
+
+fn sequence(integers: &[&BigUint]) -> Vec<u8> {
+    let mut body = vec![];
+    for x in integers {
+        push_integer(&mut body, x);
+    }
+    let mut out = vec![];
+    push_tlv(&mut out, SEQUENCE, &body);
+    out
+}
+
+/// `AlgorithmIdentifier` of `rsaEncryption` with `NULL` parameters.
+fn push_rsa_algorithm(out: &mut Vec<u8>) {
+    let mut body = vec![];
+    push_tlv(&mut body, OBJECT_IDENTIFIER, &RSA_ENCRYPTION);
+    push_tlv(&mut body, NULL, &[]);
+    push_tlv(out, SEQUENCE, &body);
+}
+
+/// Reads DER elements one after another from a byte slice.
+pub(crate) struct Reader<'a> {
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(Error::MalformedKey);
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let first = self.take(1)?[0];
+        if first < 0x80 {
+            return Ok(first as usize);
+        }
+        // 0x80 is the indefinite form, which DER forbids.
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > std::mem::size_of::<usize>() {
+            return Err(Error::MalformedKey);
+        }
+        let bytes = self.take(count)?;
+        if bytes[0] == 0 {
+            return Err(Error::MalformedKey);
+        }
+        let len = bytes
+            .iter()
+            .fold(0usize, |len, &byte| (len << 8) | byte as usize);
+        if len < 0x80 {
+            return Err(Error::MalformedKey);
+        }
+        Ok(len)
+    }
+
+    /// Contents of the next element, which must have tag `tag`.
+    pub(crate) fn read(&mut self, tag: u8) -> Result<&'a [u8]> {
+        if self.take(1)?[0] != tag {
+            return Err(Error::MalformedKey);
+        }
+        let len = self.read_len()?;
+        self.take(len)
+    }
+
+    pub(crate) fn read_integer(&mut self) -> Result<BigUint> {
+        let bytes = self.read(INTEGER)?;
+        match bytes {
+            [] => Err(Error::MalformedKey),
+            [first, ..] if first & 0x80 != 0 => Err(Error::MalformedKey),
+            [0, second, ..] if second & 0x80 == 0 => Err(Error::MalformedKey),
+            _ => Ok(BigUint::from_bytes_be(bytes)),
+        }
+    }
+
+    pub(crate) fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// `INTEGER` that must fit into a `u32`.
+    pub(crate) fn read_u32(&mut self) -> Result<u32> {
+        let x = self.read_integer()?;
+        match x.to_u32_digits()[..] {
+            [] => Ok(0),
+            [x] => Ok(x),
+            _ => Err(Error::MalformedKey),
+        }
+    }
+
+    /// `AlgorithmIdentifier`, which must be `rsaEncryption`. The parameters
+    /// must be `NULL` but may be left out, as RFC 3279 allows.
+    fn read_rsa_algorithm(&mut self) -> Result<()> {
+        let mut algorithm = Reader {
+            data: self.read(SEQUENCE)?,
+        };
+        if algorithm.read(OBJECT_IDENTIFIER)? != RSA_ENCRYPTION {
+            return Err(Error::InvalidKey("the key algorithm is not rsaEncryption"));
+        }
+        if !algorithm.data.is_empty() && !algorithm.read(NULL)?.is_empty() {
+            return Err(Error::MalformedKey);
+        }
+        algorithm.finish()
+    }
+
+    pub(crate) fn finish(&self) -> Result<()> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::MalformedKey)
+        }
+    }
+}
+
+/// Contents of `der`, which must be a single `SEQUENCE`.
+pub(crate) fn read_sequence(der: &[u8]) -> Result<Reader<'_>> {
+    let mut outer = Reader { data: der };
+    let body = outer.read(SEQUENCE)?;
+    outer.finish()?;
+    Ok(Reader { data: body })
+}
+
+pub fn encode_public_key(public_key: &RsaPublicKey) -> Vec<u8> {
+    sequence(&[public_key.n(), public_key.e()])
+}
+
+pub fn decode_public_key(der: &[u8]) -> Result<RsaPublicKey> {
+    let mut reader = read_sequence(der)?;
+    let n = reader.read_integer()?;
+    let e = reader.read_integer()?;
+    reader.finish()?;
+    RsaPublicKey::new(n, e)
+}
+
+pub fn encode_private_key(private_key: &RsaPrivateKey) -> Vec<u8> {
+    sequence(&[
+        &BigUint::zero(),
+        private_key.n(),
+        private_key.e(),
+        private_key.d(),
+        private_key.p(),
+        private_key.q(),
+        private_key.dp(),
+        private_key.dq(),
+        private_key.qinv(),
+    ])
+}
+
+/// Fails with `Error::UnsupportedVersion` for multi-prime keys and with
+/// `Error::InvalidKey` if the CRT fields do not match the primes.
+pub fn decode_private_key(der: &[u8]) -> Result<RsaPrivateKey> {
+    let mut reader = read_sequence(der)?;
+    let version = reader.read_integer()?;
+    if version == MULTI_PRIME.into() {
+        return Err(Error::UnsupportedVersion(MULTI_PRIME as u8));
+    }
+    if version != TWO_PRIME.into() {
+        return Err(Error::MalformedKey);
+    }
+    let mut fields = vec![];
+    for _ in 0..8 {
+        fields.push(reader.read_integer()?);
+    }
+    reader.finish()?;
+    let mut fields = fields.into_iter();
+    let mut next = || fields.next().unwrap();
+    let private_key = RsaPrivateKey::new(next(), next(), next(), next(), next())?;
+    if (next(), next(), next())
+        != (
+            private_key.dp().clone(),
+            private_key.dq().clone(),
+            private_key.qinv().clone(),
+        )
+    {
+        return Err(Error::InvalidKey(
+            "exponent1, exponent2 and coefficient must match the primes",
+        ));
+    }
+    Ok(private_key)
+}
+
+/// `SubjectPublicKeyInfo` holding the `RSAPublicKey`.
+pub fn encode_spki(public_key: &RsaPublicKey) -> Vec<u8> {
+    let mut key = vec![0];
+    key.extend(encode_public_key(public_key));
+    let mut body = vec![];
+    push_rsa_algorithm(&mut body);
+    push_tlv(&mut body, BIT_STRING, &key);
+    let mut out = vec![];
+    push_tlv(&mut out, SEQUENCE, &body);
+    out
+}
+
+pub fn decode_spki(der: &[u8]) -> Result<RsaPublicKey> {
+    let mut reader = read_sequence(der)?;
+    reader.read_rsa_algorithm()?;
+    let key = reader.read(BIT_STRING)?;
+    reader.finish()?;
+    // The first octet is the number of unused bits.
+    match key.split_first() {
+        Some((0, key)) => decode_public_key(key),
+        _ => Err(Error::MalformedKey),
+    }
+}
+
+/// PKCS#8 `PrivateKeyInfo` holding the `RSAPrivateKey`.
+pub fn encode_pkcs8(private_key: &RsaPrivateKey) -> Vec<u8> {
+    let mut body = vec![];
+    push_integer(&mut body, &BigUint::zero());
+    push_rsa_algorithm(&mut body);
+    push_tlv(&mut body, OCTET_STRING, &encode_private_key(private_key));
+    let mut out = vec![];
+    push_tlv(&mut out, SEQUENCE, &body);
+    out
+}
+
+/// Version 0 `PrivateKeyInfo` only; the optional attributes are not read.
+pub fn decode_pkcs8(der: &[u8]) -> Result<RsaPrivateKey> {
+    let mut reader = read_sequence(der)?;
+    let version = reader.read_integer()?;
+    if version != BigUint::zero() {
+        return Err(Error::MalformedKey);
+    }
+    reader.read_rsa_algorithm()?;
+    let key = reader.read(OCTET_STRING)?;
+    reader.finish()?;
+    decode_private_key(key)
+}
+
+#[cfg(test)]
+fn fixture_keys() -> [(RsaPrivateKey, &'static [u8], &'static [u8]); 2] {
+    [
+        (
+            crate::test_keys::rsa1024(),
+            include_bytes!("../fixtures/der/rsa1024_private_key.der"),
+            include_bytes!("../fixtures/der/rsa1024_public_key.der"),
+        ),
+        (
+            crate::test_keys::rsa2048(),
+            include_bytes!("../fixtures/der/rsa2048_private_key.der"),
+            include_bytes!("../fixtures/der/rsa2048_public_key.der"),
+        ),
+    ]
+}
+
+#[test]
+fn openssl_fixtures_test() {
+    for (private_key, private_der, public_der) in fixture_keys().iter() {
+        let public_key = private_key.to_public_key();
+        assert_eq!(&decode_private_key(private_der).unwrap(), private_key);
+        assert_eq!(decode_public_key(public_der).unwrap(), public_key);
+        assert_eq!(&encode_private_key(private_key)[..], *private_der);
+        assert_eq!(&encode_public_key(&public_key)[..], *public_der);
+    }
+}
+
+#[test]
+fn round_trip_test() {
+    let (public_key, private_key) =
+        crate::keys::get_keys(2, 64, &crate::keys::DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let private_der = encode_private_key(&private_key);
+    let public_der = encode_public_key(&public_key);
+    // Short lengths only: no long-form length octets.
+    assert!(private_der[1] < 0x80 && public_der[1] < 0x80);
+    assert_eq!(decode_private_key(&private_der).unwrap(), private_key);
+    assert_eq!(decode_public_key(&public_der).unwrap(), public_key);
+}
+
+#[test]
+fn malformed_test() {
+    let (_, private_der, public_der) = &fixture_keys()[0];
+    let mut trailing = public_der.to_vec();
+    trailing.push(0);
+    let mut indefinite = public_der.to_vec();
+    indefinite[1] = 0x80;
+    let mut long_form = vec![SEQUENCE, 0x81, 4];
+    long_form.extend_from_slice(&[INTEGER, 1, 3, INTEGER]);
+    let cases: [&[u8]; 7] = [
+        &[],
+        &public_der[..public_der.len() - 1],
+        &trailing,
+        &indefinite,
+        // Long-form length for a short value.
+        &long_form,
+        // Negative modulus, then a non-minimal exponent.
+        &[SEQUENCE, 6, INTEGER, 1, 0xff, INTEGER, 1, 3],
+        &[SEQUENCE, 7, INTEGER, 1, 0x0f, INTEGER, 2, 0, 3],
+    ];
+    for der in cases.iter() {
+        assert!(matches!(decode_public_key(der), Err(Error::MalformedKey)));
+    }
+    assert!(decode_private_key(public_der).is_err());
+    assert!(decode_public_key(private_der).is_err());
+
+    // Multi-prime version and a wrong coefficient.
+    let mut multi_prime = private_der.to_vec();
+    multi_prime[6] = 1;
+    assert!(matches!(
+        decode_private_key(&multi_prime),
+        Err(Error::UnsupportedVersion(1))
+    ));
+    let mut wrong_qinv = private_der.to_vec();
+    let last = wrong_qinv.len() - 1;
+    wrong_qinv[last] ^= 1;
+    assert!(matches!(
+        decode_private_key(&wrong_qinv),
+        Err(Error::InvalidKey(_))
+    ));
+}
+
+#[test]
+fn pkcs8_spki_test() {
+    let (private_key, _, _) = &fixture_keys()[0];
+    let public_key = private_key.to_public_key();
+    let pkcs8 = include_bytes!("../fixtures/der/rsa1024_pkcs8.der");
+    let spki = include_bytes!("../fixtures/der/rsa1024_spki.der");
+    assert_eq!(&decode_pkcs8(pkcs8).unwrap(), private_key);
+    assert_eq!(decode_spki(spki).unwrap(), public_key);
+    assert_eq!(&encode_pkcs8(private_key)[..], &pkcs8[..]);
+    assert_eq!(&encode_spki(&public_key)[..], &spki[..]);
+
+    // Parameters left out instead of NULL.
+    let mut without_null = vec![SEQUENCE, 0x81, spki[2] - 2, SEQUENCE, 11];
+    without_null.extend_from_slice(&spki[5..16]);
+    without_null.extend_from_slice(&spki[18..]);
+    assert_eq!(decode_spki(&without_null).unwrap(), public_key);
+
+    let mut other_algorithm = spki.to_vec();
+    // RSASSA-PSS, 1.2.840.113549.1.1.10.
+    other_algorithm[15] = 0x0a;
+    assert!(matches!(
+        decode_spki(&other_algorithm),
+        Err(Error::InvalidKey(_))
+    ));
+    assert!(decode_spki(pkcs8).is_err());
+    assert!(decode_pkcs8(spki).is_err());
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The value has no multiplicative inverse modulo the given modulus.
+    NotInvertible,
+    /// The ciphertext could not be parsed or decrypted; `offset` is the index
+    /// of the first bad value or chunk.
+    MalformedCiphertext {
+        offset: usize,
+    },
+    /// The encrypted file uses a container version this build cannot read.
+    UnsupportedVersion(u8),
+    /// None of the key slots of the encrypted file opens with the given key.
+    NotRecipient,
+    /// The key file could not be parsed.
+    MalformedKey,
+    /// The key components do not form a valid RSA key.
+    InvalidKey(&'static str),
+    /// The passphrase does not decrypt the encrypted private key.
+    WrongPassphrase,
+    /// The message does not fit into the key modulus.
+    MessageTooLong,
+    /// A private key operation produced a result that fails the public key check.
+    FaultDetected,
+    /// The signature does not match the message and public key.
+    InvalidSignature,
+    UnknownCommand(String),
+    /// The command needs an argument that was not given.
+    MissingArgument(&'static str),
+    InvalidArgument {
+        name: &'static str,
+        value: String,
+    },
+    Io(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Process exit code the `encoder` binary reports for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::UnknownCommand(_)
+            | Error::MissingArgument(_)
+            | Error::InvalidArgument { .. } => 2,
+            Error::Io(_) => 3,
+            Error::MalformedKey
+            | Error::InvalidKey(_)
+            | Error::WrongPassphrase
+            | Error::NotRecipient => 4,
+            Error::MalformedCiphertext { .. } | Error::UnsupportedVersion(_) => 5,
+            Error::MessageTooLong => 6,
+            Error::NotInvertible => 7,
+            Error::FaultDetected => 8,
+            Error::InvalidSignature => 9,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotInvertible => write!(f, "value is not invertible modulo the given modulus"),
+            Error::MalformedCiphertext { offset } => {
+                write!(f, "malformed ciphertext at value #{}", offset)
+            }
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported container version {}", version)
+            }
+            Error::NotRecipient => write!(f, "the key is not a recipient of this file"),
+            Error::MalformedKey => write!(f, "malformed key file"),
+            Error::InvalidKey(reason) => write!(f, "invalid key: {}", reason),
+            Error::WrongPassphrase => write!(f, "wrong passphrase for the private key"),
+            Error::MessageTooLong => write!(f, "message is too long for the key size"),
+            Error::FaultDetected => write!(f, "private key operation failed its consistency check"),
+            Error::InvalidSignature => write!(f, "signature verification failed"),
+            Error::UnknownCommand(cmd) => write!(f, "command unrecognized; command is '{}'", cmd),
+            Error::MissingArgument(name) => write!(f, "missing argument {}", name),
+            Error::InvalidArgument { name, value } => {
+                write!(f, "invalid value '{}' for {}", value, name)
+            }
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,616 @@
+//! Hybrid encryption: a random content key is wrapped with RSA and the data
+//! is encrypted with an AEAD in chunks.
+//!
+//! Container format, version 2 (all integers big-endian):
+//!
+//! | bytes | field                                                         |
+//! |-------|---------------------------------------------------------------|
+//! | 8     | magic `ENCODER\0`                                             |
+//! | 1     | version, `2`                                                  |
+//! | 1     | key wrapping: `1` RSA-OAEP, `2` RSA-KEM                       |
+//! | 1     | AEAD: `1` AES-256-GCM, `2` ChaCha20-Poly1305                  |
+//! | 4     | plaintext chunk length `L`                                    |
+//! | 7     | nonce prefix                                                  |
+//! | 2     | number of key slots, at least one                             |
+//! | ...   | key slots                                                     |
+//! | ...   | chunks                                                        |
+//!
+//! Every key slot holds the content key wrapped for one recipient:
+//!
+//! | bytes | field                                                         |
+//! |-------|---------------------------------------------------------------|
+//! | 32    | recipient key fingerprint, see `RsaPublicKey::fingerprint`    |
+//! | 2     | length of the wrapped key                                     |
+//! | ...   | wrapped key                                                   |
+//!
+//! Version 1 is still read. It has a single recipient and, in place of the
+//! slot count and slots, just the wrapped key length and the wrapped key.
+//!
+//! RSA-OAEP wraps the 32-byte content key with SHA-256, MGF1-SHA-256 and an
+//! empty label. RSA-KEM (RFC 5990) encrypts a random `z < n`, derives a key
+//! encryption key `KDF2-SHA-256(I2OSP(z, k), 32)` and wraps the content key
+//! with AES-256 key wrap (RFC 3394); the wrapped key is `C || wrapped CEK`.
+//!
+//! The body follows the STREAM construction: chunk `i` is the AEAD encryption
+//! of the next `L` plaintext bytes under the nonce `prefix || I2OSP(i, 4) ||
+//! last`, where `last` is `1` for the final chunk and `0` otherwise, with the
+//! header as associated data. The final chunk holds fewer than `L` bytes,
+//! possibly none, so truncating or reordering chunks fails authentication.
+
+use std::io::{Read, Write};
+
+use aes_gcm::aead::{Aead as _, KeyInit, Payload};
+use aes_gcm::Aes256Gcm;
+use aes_kw::KekAes256;
+use chacha20poly1305::ChaCha20Poly1305;
+use num_bigint::RandBigInt;
+use rand::RngCore;
+
+use crate::cipher::{i2osp, os2ip};
+use crate::error::{Error, Result};
+use crate::hash::Hash;
+use crate::keys::{RsaPrivateKey, RsaPublicKey};
+use crate::oaep;
+
+const MAGIC: &[u8; 8] = b"ENCODER\0";
+const VERSION: u8 = 2;
+
+/// Single-recipient containers without fingerprints.
+const VERSION_1: u8 = 1;
+
+const FINGERPRINT_LEN: usize = 32;
+
+/// Plaintext bytes per chunk.
+pub const CHUNK_LEN: usize = 64 * 1024;
+
+const KEY_LEN: usize = 32;
+const NONCE_PREFIX_LEN: usize = 7;
+const TAG_LEN: usize = 16;
+
+/// How the content key is encrypted to the RSA key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyWrap {
+    Oaep,
+    Kem,
+}
+
+/// AEAD used for the file body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aead {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl std::str::FromStr for KeyWrap {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "oaep" => Ok(KeyWrap::Oaep),
+            "kem" => Ok(KeyWrap::Kem),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::str::FromStr for Aead {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "aes256gcm" | "aes-256-gcm" => Ok(Aead::Aes256Gcm),
+            "chacha20poly1305" | "chacha20-poly1305" => Ok(Aead::ChaCha20Poly1305),
+            _ => Err(()),
+        }
+    }
+}
+
+impl KeyWrap {
+    fn id(self) -> u8 {
+        match self {
+            KeyWrap::Oaep => 1,
+            KeyWrap::Kem => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(KeyWrap::Oaep),
+            2 => Some(KeyWrap::Kem),
+            _ => None,
+        }
+    }
+
+    fn wrap(self, public_key: &RsaPublicKey, content_key: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            KeyWrap::Oaep => oaep::encrypt(public_key, Hash::Sha256, b"", content_key),
+            KeyWrap::Kem => {
+                let k = public_key.size();
+                let z = rand::thread_rng().gen_biguint_below(public_key.n());
+                let mut wrapped = i2osp(&public_key.encrypt_raw(&z)?, k);
+                let kek = kem_kek(&i2osp(&z, k));
+                wrapped.extend(kek.wrap_vec(content_key).unwrap());
+                Ok(wrapped)
+            }
+        }
+    }
+
+    fn unwrap(self, private_key: &RsaPrivateKey, wrapped: &[u8]) -> Result<Vec<u8>> {
+        let malformed = Error::MalformedCiphertext { offset: 0 };
+        match self {
+            KeyWrap::Oaep => oaep::decrypt(private_key, Hash::Sha256, b"", wrapped),
+            KeyWrap::Kem => {
+                let k = private_key.size();
+                if wrapped.len() != k + KEY_LEN + 8 {
+                    return Err(malformed);
+                }
+                let (c, wrapped_key) = wrapped.split_at(k);
+                let z = private_key.decrypt_raw(&os2ip(c))?;
+                kem_kek(&i2osp(&z, k))
+                    .unwrap_vec(wrapped_key)
+                    .map_err(|_| malformed)
+            }
+        }
+    }
+}
+
+/// KDF2 with SHA-256 (ISO 18033-2) shortened to the 32 bytes of one block.
+fn kem_kek(z: &[u8]) -> KekAes256 {
+    let mut input = z.to_vec();
+    input.extend_from_slice(&1u32.to_be_bytes());
+    let mut kek = [0; KEY_LEN];
+    kek.copy_from_slice(&Hash::Sha256.digest(&input));
+    KekAes256::from(kek)
+}
+
+impl Aead {
+    fn id(self) -> u8 {
+        match self {
+            Aead::Aes256Gcm => 1,
+            Aead::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Aead::Aes256Gcm),
+            2 => Some(Aead::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+}
+
+/// An AEAD instance keyed with the content key.
+enum Cipher {
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+impl Cipher {
+    fn new(aead: Aead, key: &[u8]) -> Self {
+        match aead {
+            Aead::Aes256Gcm => Cipher::Aes256Gcm(Box::new(Aes256Gcm::new_from_slice(key).unwrap())),
+            Aead::ChaCha20Poly1305 => {
+                Cipher::ChaCha20Poly1305(ChaCha20Poly1305::new_from_slice(key).unwrap())
+            }
+        }
+    }
+
+    fn seal(&self, nonce: &[u8; 12], aad: &[u8], msg: &[u8]) -> Vec<u8> {
+        let payload = Payload { msg, aad };
+        match self {
+            Cipher::Aes256Gcm(cipher) => cipher.encrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305(cipher) => cipher.encrypt(nonce.into(), payload),
+        }
+        .unwrap()
+    }
+
+    fn open(&self, nonce: &[u8; 12], aad: &[u8], msg: &[u8]) -> Option<Vec<u8>> {
+        let payload = Payload { msg, aad };
+        match self {
+            Cipher::Aes256Gcm(cipher) => cipher.decrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305(cipher) => cipher.decrypt(nonce.into(), payload),
+        }
+        .ok()
+    }
+}
+
+fn chunk_nonce(prefix: &[u8], index: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// Reads until `buf` is full or the input ends; returns the bytes read.
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// Encrypts everything read from `input` so that any of `recipients` can
+/// decrypt it and writes the container to `output`, holding one chunk in
+/// memory at a time.
+pub fn encrypt(
+    recipients: &[RsaPublicKey],
+    wrap: KeyWrap,
+    aead: Aead,
+    mut input: impl Read,
+    mut output: impl Write,
+) -> Result<()> {
+    if recipients.is_empty() || recipients.len() > u16::MAX as usize {
+        return Err(Error::InvalidArgument {
+            name: "recipients",
+            value: recipients.len().to_string(),
+        });
+    }
+    let mut rng = rand::thread_rng();
+    let mut content_key = [0; KEY_LEN];
+    rng.fill_bytes(&mut content_key);
+    let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+    rng.fill_bytes(&mut nonce_prefix);
+
+    let mut header = MAGIC.to_vec();
+    header.extend_from_slice(&[VERSION, wrap.id(), aead.id()]);
+    header.extend_from_slice(&(CHUNK_LEN as u32).to_be_bytes());
+    header.extend_from_slice(&nonce_prefix);
+    header.extend_from_slice(&(recipients.len() as u16).to_be_bytes());
+    for public_key in recipients {
+        let wrapped = wrap.wrap(public_key, &content_key)?;
+        header.extend_from_slice(&public_key.fingerprint());
+        header.extend_from_slice(&(wrapped.len() as u16).to_be_bytes());
+        header.extend(wrapped);
+    }
+    output.write_all(&header)?;
+
+    let cipher = Cipher::new(aead, &content_key);
+    let mut chunk = vec![0; CHUNK_LEN];
+    let mut index: u32 = 0;
+    loop {
+        let len = read_full(&mut input, &mut chunk)?;
+        let last = len < CHUNK_LEN;
+        let nonce = chunk_nonce(&nonce_prefix, index, last);
+        output.write_all(&cipher.seal(&nonce, &header, &chunk[..len]))?;
+        if last {
+            break;
+        }
+        index = index.checked_add(1).ok_or(Error::MessageTooLong)?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// Reads exactly `len` bytes and appends them to `header`.
+fn read_header_field(input: &mut impl Read, header: &mut Vec<u8>, len: usize) -> Result<Vec<u8>> {
+    let mut field = vec![0; len];
+    if read_full(input, &mut field)? != len {
+        return Err(Error::MalformedCiphertext { offset: 0 });
+    }
+    header.extend_from_slice(&field);
+    Ok(field)
+}
+
+fn read_u16(input: &mut impl Read, header: &mut Vec<u8>) -> Result<usize> {
+    let field = read_header_field(input, header, 2)?;
+    Ok(u16::from_be_bytes([field[0], field[1]]) as usize)
+}
+
+/// Decrypts a container read from `input` with `private_key` and writes the
+/// plaintext to `output` chunk by chunk. Key slots carrying the fingerprint
+/// of `private_key` are tried first, then the rest; if none opens, fails with
+/// `Error::NotRecipient`. A chunk that fails authentication stops decryption
+/// with `Error::MalformedCiphertext` carrying its index, but the chunks before
+/// it have already been written.
+pub fn decrypt(
+    private_key: &RsaPrivateKey,
+    mut input: impl Read,
+    mut output: impl Write,
+) -> Result<()> {
+    let malformed = Error::MalformedCiphertext { offset: 0 };
+    let mut header = vec![];
+    let fixed = read_header_field(
+        &mut input,
+        &mut header,
+        MAGIC.len() + 3 + 4 + NONCE_PREFIX_LEN,
+    )?;
+    if fixed[..MAGIC.len()] != MAGIC[..] {
+        return Err(malformed);
+    }
+    let fields = &fixed[MAGIC.len()..];
+    let version = fields[0];
+    if version != VERSION && version != VERSION_1 {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    let (wrap, aead) = match (KeyWrap::from_id(fields[1]), Aead::from_id(fields[2])) {
+        (Some(wrap), Some(aead)) => (wrap, aead),
+        _ => return Err(malformed),
+    };
+    let chunk_len = u32::from_be_bytes([fields[3], fields[4], fields[5], fields[6]]) as usize;
+    let nonce_prefix = fields[7..7 + NONCE_PREFIX_LEN].to_vec();
+    if chunk_len == 0 || chunk_len > CHUNK_LEN {
+        return Err(malformed);
+    }
+
+    // (fingerprint, wrapped key) of every slot; version 1 has no fingerprint.
+    let mut slots = vec![];
+    if version == VERSION_1 {
+        let wrapped_len = read_u16(&mut input, &mut header)?;
+        slots.push((
+            None,
+            read_header_field(&mut input, &mut header, wrapped_len)?,
+        ));
+    } else {
+        let slots_amount = read_u16(&mut input, &mut header)?;
+        if slots_amount == 0 {
+            return Err(malformed);
+        }
+        for _ in 0..slots_amount {
+            let fingerprint = read_header_field(&mut input, &mut header, FINGERPRINT_LEN)?;
+            let wrapped_len = read_u16(&mut input, &mut header)?;
+            let wrapped = read_header_field(&mut input, &mut header, wrapped_len)?;
+            slots.push((Some(fingerprint), wrapped));
+        }
+    }
+    let fingerprint = private_key.to_public_key().fingerprint();
+    slots
+        .sort_by_key(|(slot_fingerprint, _)| slot_fingerprint.as_deref() != Some(&fingerprint[..]));
+    let content_key = slots
+        .iter()
+        .find_map(|(_, wrapped)| wrap.unwrap(private_key, wrapped).ok())
+        .ok_or(Error::NotRecipient)?;
+
+    let cipher = Cipher::new(aead, &content_key);
+    let mut chunk = vec![0; chunk_len + TAG_LEN];
+    let mut index: u32 = 0;
+    loop {
+        let len = read_full(&mut input, &mut chunk)?;
+        let last = len < chunk.len();
+        let nonce = chunk_nonce(&nonce_prefix, index, last);
+        let plaintext =
+            cipher
+                .open(&nonce, &header, &chunk[..len])
+                .ok_or(Error::MalformedCiphertext {
+                    offset: index as usize,
+                })?;
+        output.write_all(&plaintext)?;
+        if last {
+            break;
+        }
+        index = index.checked_add(1).ok_or(Error::MessageTooLong)?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+fn encrypt_to_vec(public_key: &RsaPublicKey, wrap: KeyWrap, aead: Aead, msg: &[u8]) -> Vec<u8> {
+    let mut container = vec![];
+    encrypt(
+        std::slice::from_ref(public_key),
+        wrap,
+        aead,
+        msg,
+        &mut container,
+    )
+    .unwrap();
+    container
+}
+
+#[test]
+fn encrypt_decrypt_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let public_key = private_key.to_public_key();
+    for &wrap in [KeyWrap::Oaep, KeyWrap::Kem].iter() {
+        for &aead in [Aead::Aes256Gcm, Aead::ChaCha20Poly1305].iter() {
+            for &len in [0, 1, CHUNK_LEN - 1, CHUNK_LEN, 2 * CHUNK_LEN + 5].iter() {
+                let msg: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+                let container = encrypt_to_vec(&public_key, wrap, aead, &msg);
+                let chunks = len / CHUNK_LEN + 1;
+                assert!(container.len() > len + chunks * TAG_LEN);
+                assert!(container.len() < len + chunks * TAG_LEN + 600);
+                let mut decrypted = vec![];
+                decrypt(&private_key, &container[..], &mut decrypted).unwrap();
+                assert_eq!(decrypted, msg);
+            }
+        }
+    }
+}
+
+#[test]
+fn tampering_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let public_key = private_key.to_public_key();
+    let msg = vec![7; 2 * CHUNK_LEN + 5];
+    let container = encrypt_to_vec(&public_key, KeyWrap::Oaep, Aead::Aes256Gcm, &msg);
+    let body = container.len() - msg.len() - 3 * TAG_LEN;
+    let decrypt_to_vec = |container: &[u8]| decrypt(&private_key, container, &mut vec![]);
+
+    let mut flipped = container.clone();
+    flipped[body + CHUNK_LEN + TAG_LEN + 3] ^= 1;
+    assert!(matches!(
+        decrypt_to_vec(&flipped),
+        Err(Error::MalformedCiphertext { offset: 1 })
+    ));
+    // Dropping the final chunk makes the second one look final.
+    assert!(matches!(
+        decrypt_to_vec(&container[..body + 2 * (CHUNK_LEN + TAG_LEN)]),
+        Err(Error::MalformedCiphertext { offset: 2 })
+    ));
+    assert!(matches!(
+        decrypt_to_vec(&container[..body + CHUNK_LEN + TAG_LEN]),
+        Err(Error::MalformedCiphertext { offset: 1 })
+    ));
+    let mut swapped = container[..body].to_vec();
+    swapped.extend_from_slice(
+        &container[body + CHUNK_LEN + TAG_LEN..body + 2 * (CHUNK_LEN + TAG_LEN)],
+    );
+    swapped.extend_from_slice(&container[body..body + CHUNK_LEN + TAG_LEN]);
+    swapped.extend_from_slice(&container[body + 2 * (CHUNK_LEN + TAG_LEN)..]);
+    assert!(matches!(
+        decrypt_to_vec(&swapped),
+        Err(Error::MalformedCiphertext { offset: 0 })
+    ));
+    // The header is authenticated with every chunk.
+    let mut header = container.clone();
+    header[MAGIC.len() + 2] = Aead::ChaCha20Poly1305.id();
+    assert!(decrypt_to_vec(&header).is_err());
+    let mut version = container.clone();
+    version[MAGIC.len()] = 9;
+    assert!(matches!(
+        decrypt_to_vec(&version),
+        Err(Error::UnsupportedVersion(9))
+    ));
+    assert!(decrypt_to_vec(b"not a container").is_err());
+}
+
+#[test]
+fn wrong_key_test() {
+    let public_key = crate::test_keys::rsa2048().to_public_key();
+    for &wrap in [KeyWrap::Oaep, KeyWrap::Kem].iter() {
+        let container = encrypt_to_vec(&public_key, wrap, Aead::ChaCha20Poly1305, b"secret");
+        assert!(matches!(
+            decrypt(&crate::test_keys::rsa1024(), &container[..], &mut vec![]),
+            Err(Error::NotRecipient)
+        ));
+    }
+}
+
+#[test]
+fn recipients_test() {
+    let (first, second) = (crate::test_keys::rsa2048(), crate::test_keys::rsa1024());
+    let (third_public, third) =
+        crate::keys::get_keys(2, 512, &crate::keys::DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let recipients = [first.to_public_key(), second.to_public_key()];
+    let msg = b"for both of you";
+    for &wrap in [KeyWrap::Oaep, KeyWrap::Kem].iter() {
+        let mut container = vec![];
+        encrypt(&recipients, wrap, Aead::Aes256Gcm, &msg[..], &mut container).unwrap();
+        for private_key in [&first, &second].iter() {
+            let mut decrypted = vec![];
+            decrypt(private_key, &container[..], &mut decrypted).unwrap();
+            assert_eq!(decrypted, msg);
+        }
+        assert!(matches!(
+            decrypt(&third, &container[..], &mut vec![]),
+            Err(Error::NotRecipient)
+        ));
+        // With no fingerprint match the slots are tried anyway; the key opens
+        // its slot and only the edited header fails authentication.
+        let first_slot = MAGIC.len() + 3 + 4 + NONCE_PREFIX_LEN + 2;
+        let mut unmarked = container.clone();
+        unmarked[first_slot..first_slot + FINGERPRINT_LEN]
+            .copy_from_slice(&third_public.fingerprint());
+        assert!(matches!(
+            decrypt(&first, &unmarked[..], &mut vec![]),
+            Err(Error::MalformedCiphertext { offset: 0 })
+        ));
+    }
+    let mut container = vec![];
+    assert!(matches!(
+        encrypt(
+            &[],
+            KeyWrap::Oaep,
+            Aead::Aes256Gcm,
+            &msg[..],
+            &mut container
+        ),
+        Err(Error::InvalidArgument { .. })
+    ));
+}
+
+#[test]
+fn version_1_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let fixtures: [&[u8]; 2] = [
+        include_bytes!("../fixtures/hybrid/v1_oaep_aes256gcm.enc"),
+        include_bytes!("../fixtures/hybrid/v1_kem_chacha20poly1305.enc"),
+    ];
+    for container in fixtures.iter() {
+        assert_eq!(container[MAGIC.len()], VERSION_1);
+        let mut decrypted = vec![];
+        decrypt(&private_key, *container, &mut decrypted).unwrap();
+        assert_eq!(decrypted, include_bytes!("../fixtures/hybrid/message.txt"));
+    }
+    assert!(matches!(
+        decrypt(&crate::test_keys::rsa1024(), fixtures[0], &mut vec![]),
+        Err(Error::NotRecipient)
+    ));
+}
+
+/// Reader that yields a long run of bytes a few at a time, like a pipe.
+#[cfg(test)]
+struct TrickleReader {
+    remaining: usize,
+}
+
+#[cfg(test)]
+impl Read for TrickleReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.remaining).min(4093);
+        for byte in &mut buf[..len] {
+            *byte = (self.remaining % 256) as u8;
+            self.remaining -= 1;
+        }
+        Ok(len)
+    }
+}
+
+/// Writer that checks no write exceeds one chunk and keeps only a count.
+#[cfg(test)]
+struct BoundedWriter {
+    written: usize,
+}
+
+#[cfg(test)]
+impl Write for BoundedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        assert!(buf.len() <= CHUNK_LEN + TAG_LEN);
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn streaming_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let recipients = [private_key.to_public_key()];
+    let len = 40 * CHUNK_LEN + 17;
+    let mut output = BoundedWriter { written: 0 };
+    let input = TrickleReader { remaining: len };
+    encrypt(
+        &recipients,
+        KeyWrap::Kem,
+        Aead::ChaCha20Poly1305,
+        input,
+        &mut output,
+    )
+    .unwrap();
+    assert!(output.written > len);
+
+    let mut container = vec![];
+    let input = TrickleReader { remaining: len };
+    encrypt(
+        &recipients,
+        KeyWrap::Oaep,
+        Aead::Aes256Gcm,
+        input,
+        &mut container,
+    )
+    .unwrap();
+    let mut output = BoundedWriter { written: 0 };
+    decrypt(&private_key, &container[..], &mut output).unwrap();
+    assert_eq!(output.written, len);
+}
@@ -0,0 +1,839 @@
+use std::fmt;
+use std::sync::Mutex;
+
+use num_bigint::{BigUint, RandBigInt, ToBigUint};
+use num_traits::{one, Num};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::montgomery::MontgomeryContext;
+use crate::{der, pbes2, pem, primes};
+
+/// Public half of an RSA key pair: modulus `n` and public exponent `e`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    n_context: MontgomeryContext,
+    e: BigUint,
+}
+
+impl RsaPublicKey {
+    /// Fails with `Error::InvalidKey` if `n` is even or `e` is not in `(1, n)`.
+    pub fn new(n: BigUint, e: BigUint) -> Result<Self> {
+        if e <= one() || e >= n {
+            return Err(Error::InvalidKey(
+                "e must be greater than 1 and less than n",
+            ));
+        }
+        let n_context =
+            MontgomeryContext::new(&n).map_err(|_| Error::InvalidKey("n must be odd"))?;
+        Ok(RsaPublicKey { n_context, e })
+    }
+
+    pub fn n(&self) -> &BigUint {
+        self.n_context.modulus()
+    }
+
+    /// Length of the modulus in bytes, `k` in RFC 8017.
+    pub fn size(&self) -> usize {
+        self.n().bits().div_ceil(8) as usize
+    }
+
+    pub fn e(&self) -> &BigUint {
+        &self.e
+    }
+
+    /// SHA-256 of `I2OSP(len(n), 4) || n || I2OSP(len(e), 4) || e`, with `n`
+    /// and `e` in minimal big-endian form. Identifies the key in encrypted files.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for x in [self.n(), self.e()].iter() {
+            let bytes = x.to_bytes_be();
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(&bytes);
+        }
+        hasher.finalize().into()
+    }
+
+    /// RSAEP: `m^e mod n`.
+    pub fn encrypt_raw(&self, m: &BigUint) -> Result<BigUint> {
+        if m >= self.n() {
+            return Err(Error::MessageTooLong);
+        }
+        Ok(self.n_context.pow(m, &self.e))
+    }
+}
+
+/// Base blinding state of a private key: `(r^e mod n, r^-1 mod n)` for a
+/// random `r`, or `None` until the first private key operation.
+struct Blinding {
+    enabled: bool,
+    factor: Mutex<Option<(BigUint, BigUint)>>,
+}
+
+impl Blinding {
+    fn new() -> Self {
+        Blinding {
+            enabled: true,
+            factor: Mutex::new(None),
+        }
+    }
+}
+
+/// Clones start with a fresh blinding factor instead of sharing one.
+impl Clone for Blinding {
+    fn clone(&self) -> Self {
+        Blinding {
+            enabled: self.enabled,
+            factor: Mutex::new(None),
+        }
+    }
+}
+
+/// Blinding state is not part of the key, so it never affects equality.
+impl PartialEq for Blinding {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Blinding {}
+
+/// Private half of an RSA key pair. Keeps the factors `p`, `q` and the CRT
+/// values `dp = d mod (p-1)`, `dq = d mod (q-1)` and `qinv = q^-1 mod p`.
+///
+/// Private key operations are blinded by default; see `set_blinding`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RsaPrivateKey {
+    n_context: MontgomeryContext,
+    e: BigUint,
+    d: BigUint,
+    p_context: MontgomeryContext,
+    q_context: MontgomeryContext,
+    dp: BigUint,
+    dq: BigUint,
+    qinv: BigUint,
+    blinding: Blinding,
+}
+
+/// Shows only the modulus size and the public key fingerprint, never the
+/// secret components.
+impl fmt::Debug for RsaPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fingerprint: String = self
+            .to_public_key()
+            .fingerprint()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        f.debug_struct("RsaPrivateKey")
+            .field("bits", &self.n().bits())
+            .field("fingerprint", &fingerprint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RsaPrivateKey {
+    /// Builds a private key from the modulus, both exponents and the prime factors,
+    /// deriving the CRT values. Fails with `Error::InvalidKey` if the components
+    /// do not form a valid key.
+    pub fn new(n: BigUint, e: BigUint, d: BigUint, p: BigUint, q: BigUint) -> Result<Self> {
+        let one = &one::<BigUint>();
+        if p <= *one || q <= *one || p == q || &p * &q != n {
+            return Err(Error::InvalidKey(
+                "n must be a product of two distinct factors p and q",
+            ));
+        }
+        if e <= *one || e >= n || d <= *one || d >= n {
+            return Err(Error::InvalidKey(
+                "e and d must be greater than 1 and less than n",
+            ));
+        }
+        let (p1, q1) = (&p - one, &q - one);
+        if (&e * &d) % &p1 != *one || (&e * &d) % &q1 != *one {
+            return Err(Error::InvalidKey(
+                "d must be the inverse of e modulo p-1 and q-1",
+            ));
+        }
+        let dp = &d % &p1;
+        let dq = &d % &q1;
+        let qinv = primes::mul_inv_mod(q.clone(), p.clone())
+            .map_err(|_| Error::InvalidKey("p and q must be coprime"))?;
+        let odd = |x: &BigUint| {
+            MontgomeryContext::new(x).map_err(|_| Error::InvalidKey("n, p and q must be odd"))
+        };
+        Ok(RsaPrivateKey {
+            n_context: odd(&n)?,
+            e,
+            d,
+            p_context: odd(&p)?,
+            q_context: odd(&q)?,
+            dp,
+            dq,
+            qinv,
+            blinding: Blinding::new(),
+        })
+    }
+
+    /// Turns base blinding of `decrypt_raw` and `sign_raw` on or off. It is
+    /// on by default; switching it off is only meant for benchmarks.
+    pub fn set_blinding(&mut self, enabled: bool) {
+        self.blinding.enabled = enabled;
+    }
+
+    pub fn to_public_key(&self) -> RsaPublicKey {
+        RsaPublicKey {
+            n_context: self.n_context.clone(),
+            e: self.e.clone(),
+        }
+    }
+
+    pub fn n(&self) -> &BigUint {
+        self.n_context.modulus()
+    }
+
+    /// Length of the modulus in bytes, `k` in RFC 8017.
+    pub fn size(&self) -> usize {
+        self.n().bits().div_ceil(8) as usize
+    }
+
+    pub fn e(&self) -> &BigUint {
+        &self.e
+    }
+
+    pub fn d(&self) -> &BigUint {
+        &self.d
+    }
+
+    pub fn p(&self) -> &BigUint {
+        self.p_context.modulus()
+    }
+
+    pub fn q(&self) -> &BigUint {
+        self.q_context.modulus()
+    }
+
+    pub fn dp(&self) -> &BigUint {
+        &self.dp
+    }
+
+    pub fn dq(&self) -> &BigUint {
+        &self.dq
+    }
+
+    pub fn qinv(&self) -> &BigUint {
+        &self.qinv
+    }
+
+    /// `x^d mod n` through the CRT: `m1 = x^dp mod p`, `m2 = x^dq mod q`,
+    /// recombined with Garner's formula `m2 + q * (qinv * (m1 - m2) mod p)`.
+    /// Both exponentiations are constant-time in `dp` and `dq`.
+    fn crt_exp(&self, x: &BigUint) -> BigUint {
+        let (p, q) = (self.p(), self.q());
+        let m1 = self.p_context.pow_ct(x, &self.dp);
+        let m2 = self.q_context.pow_ct(x, &self.dq);
+        let h = (&self.qinv * (m1 + p - &m2 % p)) % p;
+        m2 + h * q
+    }
+
+    /// Takes the current blinding factor `(r^e, r^-1)`, drawing a fresh `r`
+    /// on first use, and stores its square for the next call.
+    fn next_blinding_factor(&self) -> (BigUint, BigUint) {
+        let n = self.n();
+        let mut factor = self.blinding.factor.lock().unwrap();
+        let (r_e, r_inv) = match factor.take() {
+            Some(factor) => factor,
+            None => {
+                let mut rng = rand::thread_rng();
+                loop {
+                    let r = rng.gen_biguint_range(&2u32.into(), n);
+                    if let Ok(r_inv) = primes::mul_inv_mod(r.clone(), n.clone()) {
+                        break (self.n_context.pow(&r, &self.e), r_inv);
+                    }
+                }
+            }
+        };
+        *factor = Some(((&r_e * &r_e) % n, (&r_inv * &r_inv) % n));
+        (r_e, r_inv)
+    }
+
+    /// `x^d mod n`, blinded as `(x * r^e)^d * r^-1 = x^d` unless blinding is off.
+    fn private_exp(&self, x: &BigUint) -> BigUint {
+        if !self.blinding.enabled {
+            return self.crt_exp(x);
+        }
+        let n = self.n();
+        let (r_e, r_inv) = self.next_blinding_factor();
+        (self.crt_exp(&((x * r_e) % n)) * r_inv) % n
+    }
+
+    /// RSADP: `c^d mod n`.
+    pub fn decrypt_raw(&self, c: &BigUint) -> Result<BigUint> {
+        if c >= self.n() {
+            return Err(Error::MalformedCiphertext { offset: 0 });
+        }
+        Ok(self.private_exp(c))
+    }
+
+    /// RSASP1: `m^d mod n`. The signature is checked against the public
+    /// exponent before it is returned, so a fault in one CRT half cannot leak
+    /// a factor of `n`.
+    pub fn sign_raw(&self, m: &BigUint) -> Result<BigUint> {
+        if m >= self.n() {
+            return Err(Error::MessageTooLong);
+        }
+        let s = self.private_exp(m);
+        if self.n_context.pow(&s, &self.e) != *m {
+            return Err(Error::FaultDetected);
+        }
+        Ok(s)
+    }
+}
+
+/// Smallest prime size with at least two distinct primes that have the top two bits set.
+const MIN_PRIME_BIT_SIZE: u64 = 5;
+
+/// Prime pairs `get_keys` draws before giving up on `e`. A random prime `p`
+/// has `p ≡ 1 (mod f)` with probability about `1/(f - 1)`, so a pair fails
+/// with probability about `1 - (1 - 1/(f - 1))^2` for the smallest prime
+/// factor `f` of `e`, which is 3/4 for `e = 3`. This only gives up on sizes
+/// too small for `e`, such as 5-bit primes (29 and 31, and 3 divides 30)
+/// with `e = 3`.
+const MAX_PRIME_PAIRS: usize = 100;
+
+/// Public exponent used unless the caller asks for another one.
+pub const DEFAULT_PUBLIC_EXPONENT: u32 = 65537;
+
+/// Generates a key pair with public exponent `public_exponent` whose modulus
+/// has exactly `2 * bit_size` bits. Primes `p` with `gcd(e, p - 1) != 1` are
+/// regenerated, and `d = e^-1 mod lcm(p - 1, q - 1)` as in RFC 8017. Fails
+/// with `Error::InvalidArgument` if no suitable primes turn up.
+pub fn get_keys(
+    threads_amount: usize,
+    bit_size: u64,
+    public_exponent: &BigUint,
+) -> Result<(RsaPublicKey, RsaPrivateKey)> {
+    if bit_size < MIN_PRIME_BIT_SIZE {
+        return Err(Error::InvalidArgument {
+            name: "bit_size",
+            value: bit_size.to_string(),
+        });
+    }
+    let e = public_exponent;
+    // n has 2 * bit_size bits, so this keeps e below n.
+    if *e < 3u32.to_biguint().unwrap() || !e.bit(0) || e.bits() >= 2 * bit_size {
+        return Err(Error::InvalidArgument {
+            name: "public_exponent",
+            value: e.to_string(),
+        });
+    }
+    let one = &one::<BigUint>();
+    for _ in 0..MAX_PRIME_PAIRS {
+        let p_q = primes::get_primes(2, threads_amount, bit_size)?;
+        let (p, q) = (&p_q[0], &p_q[1]);
+        let (p1, q1) = (p - one, q - one);
+        if primes::gcd(e, &p1) != *one || primes::gcd(e, &q1) != *one {
+            continue;
+        }
+        let lambda = &p1 * &q1 / primes::gcd(&p1, &q1);
+        let d = primes::mul_inv_mod(e.clone(), lambda)?;
+        let private_key = RsaPrivateKey::new(p * q, e.clone(), d, p.clone(), q.clone())?;
+        return Ok((private_key.to_public_key(), private_key));
+    }
+    Err(Error::InvalidArgument {
+        name: "public_exponent",
+        value: e.to_string(),
+    })
+}
+
+/// Private key file layout: `n e d p q` as space-separated decimal numbers.
+pub fn write_private_key(private_key: &RsaPrivateKey) -> Vec<u8> {
+    let fields = [
+        private_key.n(),
+        private_key.e(),
+        private_key.d(),
+        private_key.p(),
+        private_key.q(),
+    ];
+    let fields: Vec<String> = fields.iter().map(|x| x.to_string()).collect();
+    fields.join(" ").into_bytes()
+}
+
+fn read_fields(key_file: &[u8]) -> Result<Vec<BigUint>> {
+    String::from_utf8_lossy(key_file)
+        .split_whitespace()
+        .map(|x| BigUint::from_str_radix(x, 10).map_err(|_| Error::MalformedKey))
+        .collect()
+}
+
+pub fn read_private_key(key_file: &[u8]) -> Result<RsaPrivateKey> {
+    let fields = read_fields(key_file)?;
+    if fields.len() != 5 {
+        return Err(Error::MalformedKey);
+    }
+    let mut fields = fields.into_iter();
+    let mut next = || fields.next().unwrap();
+    RsaPrivateKey::new(next(), next(), next(), next(), next())
+}
+
+/// Public key file layout: `n e` as space-separated decimal numbers.
+pub fn write_public_key(public_key: &RsaPublicKey) -> Vec<u8> {
+    format!("{} {}", public_key.n(), public_key.e()).into_bytes()
+}
+
+/// Reads a public key file, or the public half of a private key file.
+pub fn read_public_key(key_file: &[u8]) -> Result<RsaPublicKey> {
+    let mut fields = read_fields(key_file)?;
+    match fields.len() {
+        2 => {
+            let e = fields.pop().unwrap();
+            RsaPublicKey::new(fields.pop().unwrap(), e)
+        }
+        5 => Ok(read_private_key(key_file)?.to_public_key()),
+        _ => Err(Error::MalformedKey),
+    }
+}
+
+/// Key file layouts `keygen` can write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyFormat {
+    /// Space-separated decimal numbers, see `write_private_key`.
+    Decimal,
+    /// `RSA PRIVATE KEY` and `RSA PUBLIC KEY` PEM.
+    Pkcs1,
+    /// `PRIVATE KEY` (PKCS#8) and `PUBLIC KEY` (SPKI) PEM.
+    Pkcs8,
+}
+
+impl std::str::FromStr for KeyFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "decimal" => Ok(KeyFormat::Decimal),
+            "pkcs1" => Ok(KeyFormat::Pkcs1),
+            "pkcs8" => Ok(KeyFormat::Pkcs8),
+            _ => Err(()),
+        }
+    }
+}
+
+impl KeyFormat {
+    pub fn write_private_key(self, private_key: &RsaPrivateKey) -> Vec<u8> {
+        match self {
+            KeyFormat::Decimal => write_private_key(private_key),
+            KeyFormat::Pkcs1 => {
+                pem::encode(pem::RSA_PRIVATE_KEY, &der::encode_private_key(private_key)).into()
+            }
+            KeyFormat::Pkcs8 => {
+                pem::encode(pem::PRIVATE_KEY, &der::encode_pkcs8(private_key)).into()
+            }
+        }
+    }
+
+    pub fn write_public_key(self, public_key: &RsaPublicKey) -> Vec<u8> {
+        match self {
+            KeyFormat::Decimal => write_public_key(public_key),
+            KeyFormat::Pkcs1 => {
+                pem::encode(pem::RSA_PUBLIC_KEY, &der::encode_public_key(public_key)).into()
+            }
+            KeyFormat::Pkcs8 => pem::encode(pem::PUBLIC_KEY, &der::encode_spki(public_key)).into(),
+        }
+    }
+}
+
+type Decoder<T> = fn(&[u8]) -> Result<T>;
+
+/// Result of the first decoder that recognises `der`; later ones are only
+/// tried while the earlier fail with `Error::MalformedKey`.
+fn decode_any<T>(der: &[u8], decoders: &[Decoder<T>]) -> Result<T> {
+    for decode in decoders {
+        match decode(der) {
+            Err(Error::MalformedKey) => continue,
+            result => return result,
+        }
+    }
+    Err(Error::MalformedKey)
+}
+
+/// Reads a private key in any supported layout: PEM (`RSA PRIVATE KEY`,
+/// `PRIVATE KEY` or `ENCRYPTED PRIVATE KEY`), the same in DER, or decimal.
+/// `passphrase` is only called for an encrypted key.
+pub fn load_private_key(
+    key_file: &[u8],
+    passphrase: impl FnOnce() -> Result<Vec<u8>>,
+) -> Result<RsaPrivateKey> {
+    let decrypt = |der: &[u8]| {
+        let encrypted = pbes2::EncryptedPrivateKey::from_der(der)?;
+        encrypted.decrypt(&passphrase()?)
+    };
+    if pem::is_pem(key_file) {
+        let (label, der) = pem::decode(key_file)?;
+        return match label.as_str() {
+            pem::RSA_PRIVATE_KEY => der::decode_private_key(&der),
+            pem::PRIVATE_KEY => der::decode_pkcs8(&der),
+            pem::ENCRYPTED_PRIVATE_KEY => decrypt(&der),
+            _ => Err(Error::MalformedKey),
+        };
+    }
+    // A decimal key file starts with the modulus, which has no leading zero,
+    // so a first byte of ASCII `0` (0x30) can only be a DER `SEQUENCE` tag.
+    if key_file.first() == Some(&der::SEQUENCE) {
+        return match decode_any(key_file, &[der::decode_private_key, der::decode_pkcs8]) {
+            Err(Error::MalformedKey) => decrypt(key_file),
+            result => result,
+        };
+    }
+    read_private_key(key_file)
+}
+
+/// `ENCRYPTED PRIVATE KEY` PEM of `private_key`, see `pbes2::encrypt`.
+pub fn write_encrypted_private_key(
+    private_key: &RsaPrivateKey,
+    passphrase: &[u8],
+    kdf: pbes2::Kdf,
+    cipher: pbes2::Cipher,
+) -> Result<Vec<u8>> {
+    let der = pbes2::encrypt(private_key, passphrase, kdf, cipher)?;
+    Ok(pem::encode(pem::ENCRYPTED_PRIVATE_KEY, &der).into())
+}
+
+/// `load_public_key` error for an `ENCRYPTED PRIVATE KEY`, in PEM or DER.
+const ENCRYPTED_KEY_HINT: &str = "the private key is encrypted, pass the public key instead";
+
+/// Reads a public key, or the public half of a private key, in any layout
+/// `load_private_key` accepts or as `RSA PUBLIC KEY` or `PUBLIC KEY` (SPKI).
+pub fn load_public_key(key_file: &[u8]) -> Result<RsaPublicKey> {
+    if pem::is_pem(key_file) {
+        let (label, der) = pem::decode(key_file)?;
+        return match label.as_str() {
+            pem::RSA_PUBLIC_KEY => der::decode_public_key(&der),
+            pem::PUBLIC_KEY => der::decode_spki(&der),
+            pem::ENCRYPTED_PRIVATE_KEY => Err(Error::InvalidKey(ENCRYPTED_KEY_HINT)),
+            _ => Ok(load_private_key(key_file, || Err(Error::MalformedKey))?.to_public_key()),
+        };
+    }
+    if key_file.first() == Some(&der::SEQUENCE) {
+        // Checked first, as `decode_spki` would reject its algorithm as not RSA.
+        if !matches!(
+            pbes2::EncryptedPrivateKey::from_der(key_file),
+            Err(Error::MalformedKey)
+        ) {
+            return Err(Error::InvalidKey(ENCRYPTED_KEY_HINT));
+        }
+        return decode_any(
+            key_file,
+            &[
+                der::decode_public_key,
+                der::decode_spki,
+                |der| Ok(der::decode_private_key(der)?.to_public_key()),
+                |der| Ok(der::decode_pkcs8(der)?.to_public_key()),
+            ],
+        );
+    }
+    read_public_key(key_file)
+}
+
+#[test]
+fn get_keys_test() {
+    for _ in 0..10 {
+        let (public_key, private_key) = get_keys(2, 32, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+        assert_eq!(public_key, private_key.to_public_key());
+        assert_eq!(private_key.p() * private_key.q(), *private_key.n());
+        assert_eq!(
+            (private_key.qinv() * private_key.q()) % private_key.p(),
+            one()
+        );
+        assert!(*private_key.dp() < private_key.p() - 1u32);
+        assert!(*private_key.dq() < private_key.q() - 1u32);
+    }
+}
+
+#[test]
+fn modulus_size_test() {
+    for &bit_size in [8u64, 16, 64, 128].iter() {
+        for _ in 0..20 {
+            let (public_key, _) = get_keys(2, bit_size, &17u32.into()).unwrap();
+            assert_eq!(public_key.n().bits(), 2 * bit_size);
+        }
+    }
+    assert!(matches!(
+        get_keys(2, 4, &3u32.into()),
+        Err(Error::InvalidArgument { .. })
+    ));
+    // 29 and 31 are the only 5-bit candidates and 3 divides 31 - 1.
+    assert!(matches!(
+        get_keys(2, 5, &3u32.into()),
+        Err(Error::InvalidArgument {
+            name: "public_exponent",
+            ..
+        })
+    ));
+    assert!(matches!(
+        get_keys(0, 64, &3u32.into()),
+        Err(Error::InvalidArgument {
+            name: "threads_amount",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn public_exponent_test() {
+    for _ in 0..10 {
+        let e = DEFAULT_PUBLIC_EXPONENT.into();
+        let (public_key, private_key) = get_keys(2, 64, &e).unwrap();
+        assert_eq!(*public_key.e(), e);
+        let lambda = {
+            let (p1, q1) = (private_key.p() - 1u32, private_key.q() - 1u32);
+            &p1 * &q1 / primes::gcd(&p1, &q1)
+        };
+        assert!(*private_key.d() < lambda);
+        assert_eq!((&e * private_key.d()) % &lambda, one());
+    }
+    for &e in [1u32, 2, 65536].iter() {
+        assert!(matches!(
+            get_keys(2, 64, &e.into()),
+            Err(Error::InvalidArgument { .. })
+        ));
+    }
+    // e must stay below the 16-bit modulus.
+    assert!(matches!(
+        get_keys(2, 8, &DEFAULT_PUBLIC_EXPONENT.into()),
+        Err(Error::InvalidArgument { .. })
+    ));
+}
+
+#[test]
+fn raw_operations_test() {
+    let mut rng = rand::thread_rng();
+    let (public_key, private_key) = get_keys(2, 64, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    for _ in 0..100 {
+        let m = rng.gen_biguint_below(public_key.n());
+        let c = public_key.encrypt_raw(&m).unwrap();
+        let expected = primes::powmod(&c, private_key.d(), public_key.n());
+        assert_eq!(private_key.decrypt_raw(&c).unwrap(), expected);
+        assert_eq!(expected, m);
+        let s = private_key.sign_raw(&m).unwrap();
+        assert_eq!(public_key.encrypt_raw(&s).unwrap(), m);
+    }
+    let n = public_key.n();
+    assert!(matches!(
+        public_key.encrypt_raw(n),
+        Err(Error::MessageTooLong)
+    ));
+    assert!(matches!(
+        private_key.decrypt_raw(n),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+}
+
+#[test]
+fn blinding_test() {
+    let mut rng = rand::thread_rng();
+    let (public_key, mut private_key) = get_keys(2, 64, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let n = public_key.n();
+    for _ in 0..10 {
+        let m = rng.gen_biguint_below(n);
+        let c = public_key.encrypt_raw(&m).unwrap();
+        let previous = private_key.blinding.factor.lock().unwrap().clone();
+        assert_eq!(private_key.decrypt_raw(&c).unwrap(), m);
+        let current = private_key.blinding.factor.lock().unwrap().clone().unwrap();
+        if let Some((r_e, r_inv)) = previous {
+            assert_eq!(current, ((&r_e * &r_e) % n, (&r_inv * &r_inv) % n));
+        }
+        assert_eq!(
+            (&current.0 * primes::powmod(&current.1, public_key.e(), n)) % n,
+            one()
+        );
+    }
+    assert!(private_key
+        .clone()
+        .blinding
+        .factor
+        .lock()
+        .unwrap()
+        .is_none());
+
+    private_key.set_blinding(false);
+    *private_key.blinding.factor.lock().unwrap() = None;
+    let m = rng.gen_biguint_below(n);
+    let s = private_key.sign_raw(&m).unwrap();
+    assert_eq!(public_key.encrypt_raw(&s).unwrap(), m);
+    assert!(private_key.blinding.factor.lock().unwrap().is_none());
+}
+
+#[test]
+fn sign_fault_test() {
+    let (_, mut private_key) = get_keys(2, 64, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    private_key.dq += 1u32;
+    assert!(matches!(
+        private_key.sign_raw(&12345u32.into()),
+        Err(Error::FaultDetected)
+    ));
+}
+
+#[test]
+fn inconsistent_exponents_test() {
+    use num_bigint::ToBigUint;
+    let (p, q) = (61.to_biguint().unwrap(), 53.to_biguint().unwrap());
+    let n = &p * &q;
+    // d=413 would be the inverse of e=17 modulo (p-1)*(q-1).
+    let key = RsaPrivateKey::new(n, 17.to_biguint().unwrap(), 414.to_biguint().unwrap(), p, q);
+    assert!(matches!(key, Err(Error::InvalidKey(_))));
+}
+
+#[test]
+fn private_key_file_test() {
+    let (_, private_key) = get_keys(2, 32, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let file = write_private_key(&private_key);
+    assert_eq!(read_private_key(&file).unwrap(), private_key);
+    assert!(matches!(
+        read_private_key(&file[..file.len() - 1]),
+        Err(Error::InvalidKey(_))
+    ));
+    assert!(matches!(
+        read_private_key(b"3233 17 x 61 53"),
+        Err(Error::MalformedKey)
+    ));
+    assert!(matches!(
+        read_private_key(b"3233 17"),
+        Err(Error::MalformedKey)
+    ));
+}
+
+#[test]
+fn public_key_file_test() {
+    let (public_key, private_key) = get_keys(2, 32, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let file = write_public_key(&public_key);
+    assert_eq!(read_public_key(&file).unwrap(), public_key);
+    assert_eq!(
+        read_public_key(&write_private_key(&private_key)).unwrap(),
+        public_key
+    );
+    assert!(matches!(
+        read_public_key(b"3233 17 2753"),
+        Err(Error::MalformedKey)
+    ));
+    assert!(matches!(
+        read_public_key(b"3234 17"),
+        Err(Error::InvalidKey(_))
+    ));
+}
+
+#[test]
+fn fingerprint_test() {
+    let (public_key, private_key) = get_keys(2, 32, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    assert_eq!(
+        private_key.to_public_key().fingerprint(),
+        public_key.fingerprint()
+    );
+    let (other_key, _) = get_keys(2, 32, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    assert_ne!(other_key.fingerprint(), public_key.fingerprint());
+    // n = 3233 = 0x0ca1, e = 17
+    let key = RsaPublicKey::new(3233u32.into(), 17u32.into()).unwrap();
+    let expected = Sha256::digest([0, 0, 0, 2, 0x0c, 0xa1, 0, 0, 0, 1, 17]);
+    assert_eq!(key.fingerprint()[..], expected[..]);
+}
+
+#[cfg(test)]
+fn no_passphrase() -> Result<Vec<u8>> {
+    panic!("the key is not encrypted")
+}
+
+#[test]
+fn load_key_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let public_key = private_key.to_public_key();
+    let private_files: [&[u8]; 5] = [
+        include_bytes!("../fixtures/rsa1024_private_key.txt"),
+        include_bytes!("../fixtures/der/rsa1024_private_key.der"),
+        include_bytes!("../fixtures/der/rsa1024_pkcs8.der"),
+        include_bytes!("../fixtures/pem/rsa1024_pkcs1.pem"),
+        include_bytes!("../fixtures/pem/rsa1024_pkcs8.pem"),
+    ];
+    for key_file in private_files.iter() {
+        assert_eq!(
+            load_private_key(key_file, no_passphrase).unwrap(),
+            private_key
+        );
+        assert_eq!(load_public_key(key_file).unwrap(), public_key);
+    }
+    let public_files: [&[u8]; 4] = [
+        include_bytes!("../fixtures/der/rsa1024_public_key.der"),
+        include_bytes!("../fixtures/der/rsa1024_spki.der"),
+        include_bytes!("../fixtures/pem/rsa1024_pkcs1_public.pem"),
+        include_bytes!("../fixtures/pem/rsa1024_spki.pem"),
+    ];
+    for key_file in public_files.iter() {
+        assert_eq!(load_public_key(key_file).unwrap(), public_key);
+        assert!(matches!(
+            load_private_key(key_file, no_passphrase),
+            Err(Error::MalformedKey)
+        ));
+    }
+
+    for &format in [KeyFormat::Decimal, KeyFormat::Pkcs1, KeyFormat::Pkcs8].iter() {
+        let private_file = format.write_private_key(&private_key);
+        let public_file = format.write_public_key(&public_key);
+        assert_eq!(
+            load_private_key(&private_file, no_passphrase).unwrap(),
+            private_key
+        );
+        assert_eq!(load_public_key(&public_file).unwrap(), public_key);
+    }
+    assert_eq!(
+        KeyFormat::Pkcs8.write_private_key(&private_key),
+        &include_bytes!("../fixtures/pem/rsa1024_pkcs8.pem")[..]
+    );
+}
+
+#[test]
+fn load_encrypted_key_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let pem = include_bytes!("../fixtures/pkcs8/pbkdf2_sha256_aes256cbc.pem");
+    let passphrase = || Ok(b"encoder".to_vec());
+    assert_eq!(load_private_key(pem, passphrase).unwrap(), private_key);
+    let (_, der) = pem::decode(pem).unwrap();
+    assert_eq!(load_private_key(&der, passphrase).unwrap(), private_key);
+    assert!(matches!(
+        load_private_key(pem, || Ok(b"wrong".to_vec())),
+        Err(Error::WrongPassphrase)
+    ));
+    for key_file in [&pem[..], &der].iter() {
+        assert!(matches!(
+            load_public_key(key_file),
+            Err(Error::InvalidKey(ENCRYPTED_KEY_HINT))
+        ));
+    }
+
+    let kdf = pbes2::Kdf::Scrypt {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+    let written =
+        write_encrypted_private_key(&private_key, b"secret", kdf, pbes2::Cipher::Aes256Gcm)
+            .unwrap();
+    assert_eq!(
+        load_private_key(&written, || Ok(b"secret".to_vec())).unwrap(),
+        private_key
+    );
+}
+
+#[test]
+fn private_key_debug_test() {
+    let (_, private_key) = get_keys(2, 32, &DEFAULT_PUBLIC_EXPONENT.into()).unwrap();
+    let debug = format!("{:?}", private_key);
+    assert!(debug.starts_with("RsaPrivateKey { bits: 64, fingerprint: "));
+    for secret in [
+        private_key.d(),
+        private_key.p(),
+        private_key.q(),
+        private_key.dp(),
+        private_key.dq(),
+    ]
+    .iter()
+    {
+        assert!(!debug.contains(&secret.to_string()));
+    }
+}
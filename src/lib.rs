@@ -0,0 +1,15 @@
+pub mod cipher;
+pub mod der;
+pub mod error;
+pub mod hash;
+pub mod hybrid;
+pub mod keys;
+pub mod montgomery;
+pub mod oaep;
+pub mod pbes2;
+pub mod pem;
+pub mod pkcs1v15;
+pub mod primes;
+pub mod pss;
+#[cfg(test)]
+pub(crate) mod test_keys;
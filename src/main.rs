@@ -1,41 +1,20 @@
-use std::fs::{read, write};
+use std::fs::{canonicalize, read, remove_file, rename, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
-use num_bigint::{self, BigUint, ToBigUint};
-use num_traits::{self, one, Num, ToPrimitive};
-mod primes;
+use encoder::{
+    cipher,
+    error::{Error, Result},
+    hash::Hash,
+    hybrid, keys, pkcs1v15, pss,
+};
+use num_bigint::BigUint;
 
-fn encode(public_key_d_n: (BigUint, BigUint), msg: &[u8]) -> Vec<BigUint> {
-    let (d, n) = public_key_d_n;
-    let mut encoded_msg = vec![];
-    for byte in msg {
-        let rem = primes::powmod(byte.to_biguint().unwrap().clone(), d.clone(), n.clone());
-        encoded_msg.push(rem);
-    }
-    encoded_msg
-}
-
-fn decode(private_key_e_n: (BigUint, BigUint), encoded_msg: &[BigUint]) -> Vec<BigUint> {
-    let (e, n) = private_key_e_n;
-    let mut msg = vec![];
-    for byte in encoded_msg {
-        let rem = primes::powmod(byte.clone(), e.clone(), n.clone());
-        msg.push(rem);
-    }
-    msg
-}
-
-fn get_keys(threads_amount: usize, bit_size: u64) -> ((BigUint, BigUint), (BigUint, BigUint)) {
-    let p_q = primes::get_primes(2, threads_amount, bit_size);
-    let one = &one::<BigUint>();
-    let (p, q) = (&p_q[0], &p_q[1]);
-    let n = &(p * q);
-    let f = &((p - one) * (q - one));
-    let d = primes::get_lower_and_coprime_with(f.clone());
-    let e = primes::mul_inv_mod(d.clone(), f.clone());
-    ((d, n.clone()), (e, n.clone()))
+fn parse_input<'a>() -> clap::ArgMatches<'a> {
+    app().get_matches()
 }
 
-fn parse_input<'a>() -> clap::ArgMatches<'a> {
+fn app<'a, 'b>() -> clap::App<'a, 'b> {
     use clap::{App, Arg};
     App::new("Encrypt programm")
         .arg(
@@ -47,13 +26,63 @@ fn parse_input<'a>() -> clap::ArgMatches<'a> {
         .arg(
             Arg::with_name("FILENAME")
                 .help("Sets the input file to use")
-                .required(true)
                 .takes_value(true),
         )
         .arg(
             Arg::with_name("BIT_SIZE")
                 .long("bit-size")
-                .default_value("256")
+                .help("Size of each prime in bits, 256 for encode and 1024 for encrypt by default")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("BITS")
+                .long("bits")
+                .help("Modulus size in bits for keygen")
+                .default_value("2048")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("KEY_FORMAT")
+                .long("format")
+                .help("Key file layout of keygen: decimal, pkcs1 or pkcs8 (PEM)")
+                .default_value("decimal")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ENCRYPT_KEY")
+                .long("encrypt")
+                .help("Encrypts new private keys with a passphrase (PKCS#8 PBES2)"),
+        )
+        .arg(
+            Arg::with_name("KDF")
+                .long("kdf")
+                .help("Passphrase key derivation of --encrypt: pbkdf2 or scrypt")
+                .default_value("pbkdf2")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("KEY_CIPHER")
+                .long("key-cipher")
+                .help("Private key cipher of --encrypt: aes256cbc or aes256gcm")
+                .default_value("aes256cbc")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("PASSPHRASE_ENV")
+                .long("passphrase-env")
+                .help("Environment variable holding the private key passphrase")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("PASSPHRASE_FD")
+                .long("passphrase-fd")
+                .help("File descriptor to read the private key passphrase from")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("PUBLIC_EXPONENT")
+                .long("public-exponent")
+                .default_value("65537")
                 .takes_value(true),
         )
         .arg(
@@ -62,83 +91,684 @@ fn parse_input<'a>() -> clap::ArgMatches<'a> {
                 .default_value("8")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("PADDING")
+                .long("padding")
+                .help(
+                    "Block padding of encode: pkcs1v15 or oaep; signature padding: pss or pkcs1v15",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("HASH")
+                .long("hash")
+                .help("OAEP hash of encode and signature hash: sha1, sha256, sha384 or sha512")
+                .default_value("sha256")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("LABEL")
+                .long("label")
+                .help("OAEP label of encode")
+                .default_value("")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("WRAP")
+                .long("wrap")
+                .help("Content key wrapping of encrypt: oaep or kem")
+                .default_value("oaep")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("AEAD")
+                .long("aead")
+                .help("Body cipher of encrypt: aes256gcm or chacha20poly1305")
+                .default_value("aes256gcm")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("RECIPIENT")
+                .long("recipient")
+                .help("Public key file to encrypt to; repeat for several recipients")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("KEY")
+                .long("key")
+                .help(
+                    "Private key file for decrypt and sign, public or private key file for verify",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("SIGNATURE")
+                .long("sig")
+                .help("Signature file to verify")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("SALT_LENGTH")
+                .long("salt-length")
+                .help("PSS salt length in bytes, the hash length by default")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("OUTPUT_FILE")
                 .long("output")
                 .short("o")
+                .alias("out")
                 .takes_value(true),
         )
-        .get_matches()
 }
-//TODO: make chose output file
-fn main() -> std::io::Result<()> {
-    let input = parse_input();
-    let cmd = input.value_of("COMMAND").unwrap();
-    let file_name = input.value_of("FILENAME").unwrap();
-    // println!("cmd={}", cmd);
-    // println!("file_name={}", file_name);
-    if ["e", "encode"].contains(&cmd) {
-        let file = read(file_name)?;
+fn parse_arg<T: std::str::FromStr>(input: &clap::ArgMatches, name: &'static str) -> Result<T> {
+    let value = input.value_of(name).unwrap();
+    value.parse().map_err(|_| Error::InvalidArgument {
+        name,
+        value: value.to_string(),
+    })
+}
 
-        let t1 = std::time::Instant::now();
-        let bit_size = input.value_of("BIT_SIZE").unwrap().parse().unwrap();
+fn required_arg<'a>(input: &'a clap::ArgMatches, name: &'static str) -> Result<&'a str> {
+    input.value_of(name).ok_or(Error::MissingArgument(name))
+}
+
+/// Fails on options given to a command that does not use them, so that
+/// `encrypt --padding pkcs1v15` is not taken for a request it ignores.
+fn reject_args(input: &clap::ArgMatches, names: &[&'static str]) -> Result<()> {
+    match names.iter().find(|&&name| input.occurrences_of(name) > 0) {
+        Some(&name) => Err(Error::InvalidArgument {
+            name,
+            value: input.value_of(name).unwrap().to_string(),
+        }),
+        None => Ok(()),
+    }
+}
 
-        let threads_amount = input.value_of("THREADS_AMOUNT").unwrap().parse().unwrap();
-        // println!("bit_size={}", bit_size);
-        // println!("threads_amount={}", threads_amount);
-        let (public_key, private_key) = get_keys(threads_amount, bit_size);
+/// Rejects the options `cmd` would ignore: hybrid options on the block and
+/// signature commands, block padding options on the hybrid ones, and the
+/// OAEP hash and label without `--padding oaep`.
+fn reject_unused_args(input: &clap::ArgMatches, cmd: &str) -> Result<()> {
+    const HYBRID: [&str; 3] = ["WRAP", "AEAD", "RECIPIENT"];
+    match cmd {
+        "e" | "encode" | "d" | "decode" => {
+            reject_args(input, &HYBRID)?;
+            if input.value_of("PADDING") != Some("oaep") {
+                reject_args(input, &["HASH", "LABEL"])?;
+            }
+            Ok(())
+        }
+        "encrypt" | "decrypt" => reject_args(input, &["PADDING", "HASH", "LABEL"]),
+        "sign" | "verify" => {
+            reject_args(input, &HYBRID)?;
+            reject_args(input, &["LABEL"])
+        }
+        _ => Ok(()),
+    }
+}
 
-        let t2 = std::time::Instant::now();
-        println!("Keys generated in {:?}", t2 - t1);
+fn parse_padding(input: &clap::ArgMatches) -> Result<cipher::Padding> {
+    match input.value_of("PADDING").unwrap_or("pkcs1v15") {
+        "pkcs1v15" => Ok(cipher::Padding::Pkcs1v15),
+        "oaep" => Ok(cipher::Padding::Oaep {
+            hash: parse_arg(input, "HASH")?,
+            label: input.value_of("LABEL").unwrap().as_bytes().to_vec(),
+        }),
+        value => Err(Error::InvalidArgument {
+            name: "PADDING",
+            value: value.to_string(),
+        }),
+    }
+}
 
-        let t1 = std::time::Instant::now();
+/// Signature schemes of the `sign` and `verify` commands.
+enum SignaturePadding {
+    Pss { salt_len: usize },
+    Pkcs1v15,
+}
+
+fn parse_signature_padding(input: &clap::ArgMatches) -> Result<(SignaturePadding, Hash)> {
+    let hash: Hash = parse_arg(input, "HASH")?;
+    let padding = match input.value_of("PADDING").unwrap_or("pss") {
+        "pss" => SignaturePadding::Pss {
+            salt_len: match input.value_of("SALT_LENGTH") {
+                Some(_) => parse_arg(input, "SALT_LENGTH")?,
+                None => hash.output_len(),
+            },
+        },
+        "pkcs1v15" => SignaturePadding::Pkcs1v15,
+        value => {
+            return Err(Error::InvalidArgument {
+                name: "PADDING",
+                value: value.to_string(),
+            })
+        }
+    };
+    Ok((padding, hash))
+}
+
+/// Opens `path` for reading; `-` is standard input.
+fn open_input(path: &str) -> Result<Box<dyn Read>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin()));
+    }
+    Ok(Box::new(File::open(path)?))
+}
+
+/// Output of a command. A file is written under a temporary name in its
+/// directory and only renamed over `path` by `finish`, so a failed run leaves
+/// the old contents in place.
+struct Output {
+    writer: Box<dyn Write>,
+    /// Temporary file and the path it replaces.
+    rename: Option<(PathBuf, PathBuf)>,
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Output {
+    /// Flushes the output and puts a file in place. Dropping an `Output`
+    /// without calling this discards what was written to a file.
+    fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        if let Some((temp, path)) = self.rename.take() {
+            rename(temp, path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if let Some((temp, _)) = &self.rename {
+            let _ = remove_file(temp);
+        }
+    }
+}
+
+/// Creates `path` for the output read from `input`; `-` is standard output.
+/// Refuses to write over the input itself.
+fn create_output(input: &str, path: &str) -> Result<Output> {
+    if path == "-" {
+        return Ok(Output {
+            writer: Box::new(BufWriter::new(io::stdout())),
+            rename: None,
+        });
+    }
+    if input != "-" {
+        if let (Ok(input), Ok(output)) = (canonicalize(input), canonicalize(path)) {
+            if input == output {
+                return Err(Error::InvalidArgument {
+                    name: "OUTPUT_FILE",
+                    value: path.to_string(),
+                });
+            }
+        }
+    }
+    create_file(path, 0o666)
+}
+
+/// Creates an `Output` for the file `path`, with permissions `mode` on unix
+/// before the umask. Only the temporary file is opened, so an existing `path`
+/// gets `mode` too once `finish` renames it over.
+fn create_file(path: &str, mode: u32) -> Result<Output> {
+    let path = PathBuf::from(path);
+    let name = path.file_name().ok_or_else(|| Error::InvalidArgument {
+        name: "OUTPUT_FILE",
+        value: path.to_string_lossy().into_owned(),
+    })?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    let file = options.open(&temp)?;
+    Ok(Output {
+        writer: Box::new(file),
+        rename: Some((temp, path)),
+    })
+}
+
+/// Reads all of `path`; `-` is standard input.
+fn read_input(path: &str) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    open_input(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// `dir/name` with `prefix` put in front of `name`.
+fn prefixed_path(path: &str, prefix: &str) -> String {
+    let path = Path::new(path);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}{}", prefix, name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `dir/name` for `dir/<prefix>name`, `path` itself if it has no `prefix`.
+fn unprefixed_path(path: &str, prefix: &str) -> String {
+    let path = Path::new(path);
+    match path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(prefix))
+    {
+        Some(name) if !name.is_empty() => path.with_file_name(name).to_string_lossy().into_owned(),
+        _ => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Key file saved next to `file_name`, or `<prefix>stdin` for standard input.
+fn key_path(file_name: &str, prefix: &str) -> String {
+    if file_name == "-" {
+        prefix.to_string() + "stdin"
+    } else {
+        prefixed_path(file_name, prefix)
+    }
+}
+
+/// `-o` if given, standard output for standard input, `default` otherwise.
+fn output_path(input: &clap::ArgMatches, file_name: &str, default: &dyn Fn() -> String) -> String {
+    match input.value_of("OUTPUT_FILE") {
+        Some(path) => path.to_string(),
+        None if file_name == "-" => "-".to_string(),
+        None => default(),
+    }
+}
+
+/// Replaces `path` with `contents` through `create_file`.
+fn write_file(path: &str, contents: &[u8], mode: u32) -> Result<()> {
+    let mut out = create_file(path, mode)?;
+    out.write_all(contents)?;
+    out.finish()
+}
+
+/// Writes a private key file readable by its owner only, even when it
+/// replaces a file that others could read.
+fn write_private_key_file(path: &str, key_file: &[u8]) -> Result<()> {
+    write_file(path, key_file, 0o600)
+}
+
+fn read_public_key_file(path: &str) -> Result<keys::RsaPublicKey> {
+    keys::load_public_key(&read(path)?)
+}
+
+/// Loads a private key, asking for the passphrase if it is encrypted.
+fn read_private_key_file(input: &clap::ArgMatches, path: &str) -> Result<keys::RsaPrivateKey> {
+    keys::load_private_key(&read(path)?, || {
+        read_passphrase(input, &format!("Passphrase for {}: ", path), false)
+    })
+}
+
+/// First line of the `--passphrase-fd` descriptor. Reads a byte at a time
+/// from a duplicate of it, so that nothing past the line is taken from a pipe
+/// and the descriptor itself stays open.
+#[cfg(unix)]
+fn read_passphrase_fd(input: &clap::ArgMatches) -> Result<Vec<u8>> {
+    use std::os::unix::io::{BorrowedFd, RawFd};
+
+    let fd: RawFd = parse_arg(input, "PASSPHRASE_FD")?;
+    // SAFETY: `F_GETFD` only reads the descriptor flags and fails on a
+    // descriptor that is not open.
+    if fd < 0 || unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(Error::InvalidArgument {
+            name: "PASSPHRASE_FD",
+            value: fd.to_string(),
+        });
+    }
+    // SAFETY: `fd` is open, as checked above, and nothing in this process
+    // closes it while the borrow lives: it is only used to duplicate it.
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    let mut file = File::from(fd.try_clone_to_owned()?);
+    let mut line = vec![];
+    let mut byte = [0];
+    while file.read(&mut byte)? == 1 && byte[0] != b'\n' {
+        line.push(byte[0]);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(line)
+}
 
-        let encoded = encode(public_key, &file);
+#[cfg(not(unix))]
+fn read_passphrase_fd(input: &clap::ArgMatches) -> Result<Vec<u8>> {
+    Err(Error::InvalidArgument {
+        name: "PASSPHRASE_FD",
+        value: input.value_of("PASSPHRASE_FD").unwrap().to_string(),
+    })
+}
+
+/// Passphrase from `--passphrase-env`, the first line of `--passphrase-fd`
+/// or the terminal. A new passphrase (`confirm`) must not be empty and is
+/// asked for twice on the terminal.
+fn read_passphrase(input: &clap::ArgMatches, prompt: &str, confirm: bool) -> Result<Vec<u8>> {
+    let (name, passphrase) = if let Some(var) = input.value_of("PASSPHRASE_ENV") {
+        let passphrase = std::env::var(var).map_err(|_| Error::InvalidArgument {
+            name: "PASSPHRASE_ENV",
+            value: var.to_string(),
+        })?;
+        ("PASSPHRASE_ENV", passphrase.into_bytes())
+    } else if input.value_of("PASSPHRASE_FD").is_some() {
+        ("PASSPHRASE_FD", read_passphrase_fd(input)?)
+    } else {
+        loop {
+            let passphrase = rpassword::prompt_password(prompt)?;
+            if !confirm {
+                return Ok(passphrase.into_bytes());
+            }
+            if passphrase.is_empty() {
+                eprintln!("The passphrase must not be empty.");
+                continue;
+            }
+            if rpassword::prompt_password("Repeat the passphrase: ")? == passphrase {
+                return Ok(passphrase.into_bytes());
+            }
+            eprintln!("The passphrases do not match.");
+        }
+    };
+    if confirm && passphrase.is_empty() {
+        return Err(Error::InvalidArgument {
+            name,
+            value: input.value_of(name).unwrap().to_string(),
+        });
+    }
+    Ok(passphrase)
+}
+
+/// Private key file contents: `ENCRYPTED PRIVATE KEY` with `--encrypt`,
+/// `format` otherwise.
+fn private_key_file(
+    input: &clap::ArgMatches,
+    path: &str,
+    private_key: &keys::RsaPrivateKey,
+    format: keys::KeyFormat,
+) -> Result<Vec<u8>> {
+    if !input.is_present("ENCRYPT_KEY") {
+        return Ok(format.write_private_key(private_key));
+    }
+    let kdf = parse_arg(input, "KDF")?;
+    let cipher = parse_arg(input, "KEY_CIPHER")?;
+    let passphrase = read_passphrase(input, &format!("New passphrase for {}: ", path), true)?;
+    keys::write_encrypted_private_key(private_key, &passphrase, kdf, cipher)
+}
+
+/// Generates a key pair, saves it next to `file_name` and returns it.
+fn generate_keys(
+    input: &clap::ArgMatches,
+    file_name: &str,
+    default_bit_size: u64,
+) -> Result<(keys::RsaPublicKey, keys::RsaPrivateKey)> {
+    let t1 = std::time::Instant::now();
+    let bit_size = match input.value_of("BIT_SIZE") {
+        Some(_) => parse_arg(input, "BIT_SIZE")?,
+        None => default_bit_size,
+    };
+    let threads_amount = parse_arg(input, "THREADS_AMOUNT")?;
+    let public_exponent: BigUint = parse_arg(input, "PUBLIC_EXPONENT")?;
+    let (public_key, private_key) = keys::get_keys(threads_amount, bit_size, &public_exponent)?;
+    let private_key_path = key_path(file_name, "private_key_");
+    let key_file = private_key_file(
+        input,
+        &private_key_path,
+        &private_key,
+        keys::KeyFormat::Decimal,
+    )?;
+    write_private_key_file(&private_key_path, &key_file)?;
+    write_file(
+        &key_path(file_name, "public_key_"),
+        &keys::write_public_key(&public_key),
+        0o666,
+    )?;
+
+    let t2 = std::time::Instant::now();
+    eprintln!("Keys generated in {:?}", t2 - t1);
+    Ok((public_key, private_key))
+}
 
-        let mut encoded_msg = vec![];
-        for val in encoded {
-            let s = val.to_string();
-            encoded_msg.extend(s.into_bytes());
-            encoded_msg.extend(b" ");
+fn run() -> Result<()> {
+    let input = parse_input();
+    let cmd = input.value_of("COMMAND").unwrap();
+    reject_unused_args(&input, cmd)?;
+    if cmd == "keygen" {
+        let bits: u64 = parse_arg(&input, "BITS")?;
+        if !bits.is_multiple_of(2) {
+            return Err(Error::InvalidArgument {
+                name: "BITS",
+                value: bits.to_string(),
+            });
         }
+        let out = required_arg(&input, "OUTPUT_FILE")?;
+        let threads_amount = parse_arg(&input, "THREADS_AMOUNT")?;
+        let public_exponent: BigUint = parse_arg(&input, "PUBLIC_EXPONENT")?;
+        let format: keys::KeyFormat = parse_arg(&input, "KEY_FORMAT")?;
+
+        let t1 = std::time::Instant::now();
+        let (public_key, private_key) = keys::get_keys(threads_amount, bits / 2, &public_exponent)?;
+        write_private_key_file(out, &private_key_file(&input, out, &private_key, format)?)?;
+        write_file(
+            &(out.to_string() + ".pub"),
+            &format.write_public_key(&public_key),
+            0o666,
+        )?;
+        let t2 = std::time::Instant::now();
+        eprintln!("Keys generated in {:?}", t2 - t1);
+
+        let fingerprint: String = public_key
+            .fingerprint()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        println!("Key fingerprint: {}", fingerprint);
+        return Ok(());
+    }
+    let file_name = required_arg(&input, "FILENAME")?;
+    if ["e", "encode"].contains(&cmd) {
+        let file = read_input(file_name)?;
+        let padding = parse_padding(&input)?;
+        let public_key = match input.value_of("KEY") {
+            Some(path) => read_public_key_file(path)?,
+            None => generate_keys(&input, file_name, 256)?.0,
+        };
 
-        let mut prk = vec![];
-        prk.extend(private_key.0.to_string().into_bytes());
-        prk.extend(b" ");
-        prk.extend(private_key.1.to_string().into_bytes());
+        let t1 = std::time::Instant::now();
 
-        write("encoded_".to_string() + file_name, encoded_msg)?;
-        write("private_key_".to_string() + file_name, prk)?;
+        let encoded = cipher::encode(&public_key, &padding, &file)?;
+        let encoded_msg = cipher::write_ciphertext(&encoded);
+        let output = output_path(&input, file_name, &|| prefixed_path(file_name, "encoded_"));
+        let mut out = create_output(file_name, &output)?;
+        out.write_all(&encoded_msg)?;
+        out.finish()?;
 
         let t2 = std::time::Instant::now();
-        println!("Message encoded in {:?}", t2 - t1);
+        eprintln!("Message encoded in {:?}", t2 - t1);
     } else if ["d", "decode"].contains(&cmd) {
+        let padding = parse_padding(&input)?;
         let t1 = std::time::Instant::now();
 
-        let file = read("encoded_".to_string() + file_name)?;
-        let key_file = read("private_key_".to_string() + file_name)?;
+        // `file_name` is the ciphertext, named `encoded_<original name>` by default.
+        let original = unprefixed_path(file_name, "encoded_");
+        let file = read_input(file_name)?;
+        let private_key = match input.value_of("KEY") {
+            Some(path) => read_private_key_file(&input, path)?,
+            None => read_private_key_file(&input, &key_path(&original, "private_key_"))?,
+        };
+        let symbols = cipher::read_ciphertext(&file)?;
 
-        let en: Vec<BigUint> = key_file
-            .split(|&x| x == b' ')
-            .map(|x| BigUint::from_str_radix(&String::from_utf8_lossy(x), 10).unwrap())
-            .collect();
-        let private_key = (en[0].clone(), en[1].clone());
-        let symbols: Vec<BigUint> = file
-            .split(|&x| x == b' ')
-            .filter(|x| String::from_utf8_lossy(x).trim() != "")
-            .map(|x| BigUint::from_str_radix(String::from_utf8_lossy(x).trim(), 10).unwrap())
-            .collect();
+        let decoded_msg = cipher::decode(&private_key, &padding, &symbols)?;
+        let output = output_path(&input, file_name, &|| prefixed_path(&original, "decoded_"));
+        let mut out = create_output(file_name, &output)?;
+        out.write_all(&decoded_msg)?;
+        out.finish()?;
 
-        let decoded_msg: Vec<u8> = decode(private_key, &symbols)
-            .iter()
-            .map(|x| x.to_u8().unwrap())
-            .collect();
-        write("decoded_".to_string() + file_name, decoded_msg)?;
+        let t2 = std::time::Instant::now();
+        eprintln!("Message decoded in {:?}", t2 - t1);
+    } else if cmd == "encrypt" {
+        let wrap = parse_arg(&input, "WRAP")?;
+        let aead = parse_arg(&input, "AEAD")?;
+        let file = open_input(file_name)?;
+        let mut recipients = input
+            .values_of("KEY")
+            .into_iter()
+            .chain(input.values_of("RECIPIENT"))
+            .flatten()
+            .map(read_public_key_file)
+            .collect::<Result<Vec<_>>>()?;
+        if recipients.is_empty() {
+            recipients.push(generate_keys(&input, file_name, 1024)?.0);
+        }
 
+        let t1 = std::time::Instant::now();
+        let output = output_path(&input, file_name, &|| {
+            prefixed_path(file_name, "encrypted_")
+        });
+        let mut out = create_output(file_name, &output)?;
+        hybrid::encrypt(&recipients, wrap, aead, file, &mut out)?;
+        out.finish()?;
         let t2 = std::time::Instant::now();
-        println!("Message decoded in {:?}", t2 - t1);
+        eprintln!("Message encrypted in {:?}", t2 - t1);
+    } else if cmd == "decrypt" {
+        let t1 = std::time::Instant::now();
+        // `file_name` is the container, named `encrypted_<original name>` by default.
+        let original = unprefixed_path(file_name, "encrypted_");
+        let private_key = match input.value_of("KEY") {
+            Some(path) => read_private_key_file(&input, path)?,
+            None => read_private_key_file(&input, &key_path(&original, "private_key_"))?,
+        };
+        let output = output_path(&input, file_name, &|| {
+            prefixed_path(&original, "decrypted_")
+        });
+        let mut out = create_output(file_name, &output)?;
+        hybrid::decrypt(&private_key, open_input(file_name)?, &mut out)?;
+        out.finish()?;
+        let t2 = std::time::Instant::now();
+        eprintln!("Message decrypted in {:?}", t2 - t1);
+    } else if cmd == "sign" {
+        let (padding, hash) = parse_signature_padding(&input)?;
+        let private_key = read_private_key_file(&input, required_arg(&input, "KEY")?)?;
+        let file = read_input(file_name)?;
+        let signature = match padding {
+            SignaturePadding::Pss { salt_len } => pss::sign(&private_key, hash, salt_len, &file)?,
+            SignaturePadding::Pkcs1v15 => pkcs1v15::sign(&private_key, hash, &file)?,
+        };
+        let output = output_path(&input, file_name, &|| file_name.to_string() + ".sig");
+        let mut out = create_output(file_name, &output)?;
+        out.write_all(&signature)?;
+        out.finish()?;
+    } else if cmd == "verify" {
+        let (padding, hash) = parse_signature_padding(&input)?;
+        let public_key = read_public_key_file(required_arg(&input, "KEY")?)?;
+        let signature = read(required_arg(&input, "SIGNATURE")?)?;
+        let file = read_input(file_name)?;
+        match padding {
+            SignaturePadding::Pss { salt_len } => {
+                pss::verify(&public_key, hash, salt_len, &file, &signature)?
+            }
+            SignaturePadding::Pkcs1v15 => pkcs1v15::verify(&public_key, hash, &file, &signature)?,
+        }
+        println!("Signature is valid");
     } else {
-        panic!("Command unrecognized; command is '{}'", cmd);
+        return Err(Error::UnknownCommand(cmd.to_string()));
     }
     Ok(())
 }
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+#[test]
+fn reject_unused_args_test() {
+    let check = |args: &[&str]| {
+        let input = app().get_matches_from([&["encoder"], args].concat());
+        reject_unused_args(&input, args[0])
+    };
+    let rejected = [
+        (&["encode", "f", "--wrap", "kem"][..], "WRAP"),
+        (&["e", "f", "--label", "l"], "LABEL"),
+        (&["decode", "f", "--aead", "aes256gcm"], "AEAD"),
+        (
+            &["d", "f", "--padding", "pkcs1v15", "--hash", "sha1"],
+            "HASH",
+        ),
+        (&["encrypt", "f", "--padding", "oaep"], "PADDING"),
+        (&["decrypt", "f", "--label", "l"], "LABEL"),
+        (&["sign", "f", "--key", "k", "--aead", "aes256gcm"], "AEAD"),
+        (&["sign", "f", "--key", "k", "--label", "l"], "LABEL"),
+        (
+            &["verify", "f", "--key", "k", "--recipient", "r"],
+            "RECIPIENT",
+        ),
+    ];
+    for (args, expected) in rejected.iter() {
+        match check(args) {
+            Err(Error::InvalidArgument { name, .. }) => assert_eq!(name, *expected),
+            result => panic!("{:?}: {:?}", args, result.map_err(|err| err.to_string())),
+        }
+    }
+    let accepted: [&[&str]; 5] = [
+        &[
+            "encode",
+            "f",
+            "--padding",
+            "oaep",
+            "--hash",
+            "sha1",
+            "--label",
+            "l",
+        ],
+        &["decode", "f", "--padding", "oaep"],
+        &["encrypt", "f", "--wrap", "kem", "--recipient", "r"],
+        &["decrypt", "f", "--key", "k"],
+        &[
+            "sign",
+            "f",
+            "--key",
+            "k",
+            "--padding",
+            "pss",
+            "--hash",
+            "sha512",
+        ],
+    ];
+    for args in accepted.iter() {
+        assert!(check(args).is_ok(), "{:?}", args);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn write_private_key_file_test() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("encoder_key_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("k2");
+    std::fs::write(&path, b"old").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    write_private_key_file(path.to_str().unwrap(), b"new").unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(read(&path).unwrap(), b"new");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
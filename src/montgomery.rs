@@ -0,0 +1,391 @@
+use num_bigint::BigUint;
+use num_traits::{one, zero};
+
+use crate::error::{Error, Result};
+
+/// Precomputed values for Montgomery multiplication modulo an odd `modulus`,
+/// working on little-endian `u64` limbs with `R = 2^(64 * limbs)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MontgomeryContext {
+    modulus: BigUint,
+    limbs: Vec<u64>,
+    /// `-modulus^-1 mod 2^64`.
+    m_inv: u64,
+    /// `R^2 mod modulus`, to move numbers into Montgomery form.
+    r2: Vec<u64>,
+}
+
+/// `a + b * c + carry`, split into the low and high limb.
+#[inline(always)]
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + (b as u128) * (c as u128) + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+/// Window width of the fixed-window exponentiation in `pow_ct`.
+const CT_WINDOW: usize = 4;
+
+/// All-ones if `a == b`, zero otherwise, without branching on the values.
+/// The result goes through `black_box` so the optimizer cannot turn the
+/// masked selects that use it back into branches.
+#[inline(always)]
+fn ct_eq_mask(a: u64, b: u64) -> u64 {
+    let x = a ^ b;
+    // x | -x has its top bit set exactly when x != 0.
+    std::hint::black_box(((x | x.wrapping_neg()) >> 63).wrapping_sub(1))
+}
+
+/// Window width for sliding-window exponentiation with an exponent of `bits` bits.
+fn window_size(bits: u64) -> u64 {
+    match bits {
+        b if b > 671 => 6,
+        b if b > 239 => 5,
+        b if b > 79 => 4,
+        b if b > 23 => 3,
+        _ => 1,
+    }
+}
+
+impl MontgomeryContext {
+    /// Fails with `Error::InvalidArgument` unless `modulus` is odd and greater than 1.
+    pub fn new(modulus: &BigUint) -> Result<Self> {
+        if !modulus.bit(0) || *modulus == one() {
+            return Err(Error::InvalidArgument {
+                name: "modulus",
+                value: modulus.to_string(),
+            });
+        }
+        let limbs = modulus.to_u64_digits();
+        let mut inv: u64 = 1;
+        // Newton iteration doubles the number of correct low bits each step.
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(limbs[0].wrapping_mul(inv)));
+        }
+        let r2 = (one::<BigUint>() << (128 * limbs.len())) % modulus;
+        let mut context = MontgomeryContext {
+            modulus: modulus.clone(),
+            limbs,
+            m_inv: inv.wrapping_neg(),
+            r2: vec![],
+        };
+        context.r2 = context.to_limbs(&r2);
+        Ok(context)
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    /// `x` (already below the modulus) as a fixed-width limb vector.
+    fn to_limbs(&self, x: &BigUint) -> Vec<u64> {
+        let mut limbs = x.to_u64_digits();
+        limbs.resize(self.limbs.len(), 0);
+        limbs
+    }
+
+    fn from_limbs(limbs: &[u64]) -> BigUint {
+        let digits: Vec<u32> = limbs
+            .iter()
+            .flat_map(|&limb| vec![limb as u32, (limb >> 32) as u32])
+            .collect();
+        BigUint::new(digits)
+    }
+
+    /// `out = a * b * R^-1 mod modulus` (CIOS method). `t` is scratch space of
+    /// `limbs + 2` words. The final subtraction is done with a mask, so the
+    /// running time does not depend on the operands.
+    fn mul(&self, a: &[u64], b: &[u64], out: &mut [u64], t: &mut [u64]) {
+        let m = &self.limbs;
+        let len = m.len();
+        for x in t.iter_mut() {
+            *x = 0;
+        }
+        for &b_i in b.iter() {
+            let mut carry = 0;
+            for j in 0..len {
+                let (lo, hi) = mac(t[j], a[j], b_i, carry);
+                t[j] = lo;
+                carry = hi;
+            }
+            let (sum, overflow) = t[len].overflowing_add(carry);
+            t[len] = sum;
+            t[len + 1] = overflow as u64;
+
+            let k = t[0].wrapping_mul(self.m_inv);
+            let (_, mut carry) = mac(t[0], k, m[0], 0);
+            for j in 1..len {
+                let (lo, hi) = mac(t[j], k, m[j], carry);
+                t[j - 1] = lo;
+                carry = hi;
+            }
+            let (sum, overflow) = t[len].overflowing_add(carry);
+            t[len - 1] = sum;
+            t[len] = t[len + 1] + overflow as u64;
+        }
+        // t < 2 * modulus here; subtract once and keep the difference unless it borrowed.
+        let mut borrow = 0;
+        for j in 0..len {
+            let (diff, b1) = t[j].overflowing_sub(m[j]);
+            let (diff, b2) = diff.overflowing_sub(borrow);
+            out[j] = diff;
+            borrow = (b1 | b2) as u64;
+        }
+        let (_, b1) = t[len].overflowing_sub(borrow);
+        let keep_t = std::hint::black_box((b1 as u64).wrapping_neg());
+        for j in 0..len {
+            out[j] = (t[j] & keep_t) | (out[j] & !keep_t);
+        }
+    }
+
+    /// `base^exp mod modulus` with sliding-window exponentiation. Runs in time
+    /// that depends on `exp`, so it is meant for public exponents.
+    pub fn pow(&self, base: &BigUint, exp: &BigUint) -> BigUint {
+        let len = self.limbs.len();
+        let mut t = vec![0; len + 2];
+        let mut one_limbs = vec![0; len];
+        one_limbs[0] = 1;
+
+        let base = self.to_limbs(&(base % &self.modulus));
+        let mut x = vec![0; len];
+        self.mul(&base, &self.r2, &mut x, &mut t);
+
+        // acc = R mod modulus, i.e. 1 in Montgomery form.
+        let mut acc = vec![0; len];
+        self.mul(&self.r2, &one_limbs, &mut acc, &mut t);
+        if *exp == zero() {
+            let mut out = vec![0; len];
+            self.mul(&acc, &one_limbs, &mut out, &mut t);
+            return Self::from_limbs(&out);
+        }
+
+        // Odd powers x, x^3, x^5, ..., x^(2^w - 1).
+        let w = window_size(exp.bits());
+        let mut x2 = vec![0; len];
+        self.mul(&x, &x, &mut x2, &mut t);
+        let mut table = vec![x];
+        for i in 1..1 << (w - 1) {
+            let mut next = vec![0; len];
+            self.mul(&table[i - 1], &x2, &mut next, &mut t);
+            table.push(next);
+        }
+
+        let mut tmp = vec![0; len];
+        let mut i = exp.bits() as i64 - 1;
+        while i >= 0 {
+            if !exp.bit(i as u64) {
+                self.mul(&acc, &acc, &mut tmp, &mut t);
+                std::mem::swap(&mut acc, &mut tmp);
+                i -= 1;
+                continue;
+            }
+            let mut low = (i - w as i64 + 1).max(0);
+            while !exp.bit(low as u64) {
+                low += 1;
+            }
+            let mut window = 0;
+            for bit in (low..=i).rev() {
+                self.mul(&acc, &acc, &mut tmp, &mut t);
+                std::mem::swap(&mut acc, &mut tmp);
+                window = (window << 1) | exp.bit(bit as u64) as usize;
+            }
+            self.mul(&acc, &table[window >> 1], &mut tmp, &mut t);
+            std::mem::swap(&mut acc, &mut tmp);
+            i = low - 1;
+        }
+
+        self.mul(&acc, &one_limbs, &mut tmp, &mut t);
+        Self::from_limbs(&tmp)
+    }
+
+    /// `base^exp mod modulus` for a secret `exp` below `2^(64 * limbs)`.
+    /// Panics on a wider `exp`, whose high limbs the window loop would skip.
+    ///
+    /// Fixed-window exponentiation over the full limb width: every call does
+    /// the same squarings and multiplications in the same order, and table
+    /// entries are picked by scanning the whole table with masks, so neither
+    /// the branches nor the memory accesses depend on the exponent.
+    pub fn pow_ct(&self, base: &BigUint, exp: &BigUint) -> BigUint {
+        let len = self.limbs.len();
+        assert!(
+            exp.bits() <= 64 * len as u64,
+            "pow_ct exponent is wider than the modulus"
+        );
+        let mut t = vec![0; len + 2];
+        let mut one_limbs = vec![0; len];
+        one_limbs[0] = 1;
+        let mut exp_limbs = exp.to_u64_digits();
+        exp_limbs.resize(len, 0);
+
+        // table[i] = base^i in Montgomery form.
+        let base = self.to_limbs(&(base % &self.modulus));
+        let mut table = vec![vec![0; len]; 1 << CT_WINDOW];
+        self.mul(&self.r2, &one_limbs, &mut table[0], &mut t);
+        self.mul(&base, &self.r2, &mut table[1], &mut t);
+        for i in 2..1 << CT_WINDOW {
+            let mut next = vec![0; len];
+            self.mul(&table[i - 1], &table[1], &mut next, &mut t);
+            table[i] = next;
+        }
+
+        let mut acc = table[0].clone();
+        let mut tmp = vec![0; len];
+        let mut selected = vec![0; len];
+        for limb in exp_limbs.iter().rev() {
+            for shift in (0..64 / CT_WINDOW).rev() {
+                for _ in 0..CT_WINDOW {
+                    self.mul(&acc, &acc, &mut tmp, &mut t);
+                    std::mem::swap(&mut acc, &mut tmp);
+                }
+                let window = (limb >> (shift * CT_WINDOW)) & ((1 << CT_WINDOW) - 1);
+                for x in selected.iter_mut() {
+                    *x = 0;
+                }
+                for (i, entry) in table.iter().enumerate() {
+                    let mask = ct_eq_mask(i as u64, window);
+                    for (x, &e) in selected.iter_mut().zip(entry.iter()) {
+                        *x |= e & mask;
+                    }
+                }
+                self.mul(&acc, &selected, &mut tmp, &mut t);
+                std::mem::swap(&mut acc, &mut tmp);
+            }
+        }
+
+        self.mul(&acc, &one_limbs, &mut tmp, &mut t);
+        Self::from_limbs(&tmp)
+    }
+}
+
+#[test]
+fn pow_test() {
+    use num_bigint::RandBigInt;
+    let mut rng = rand::thread_rng();
+    for &bits in [2u64, 17, 64, 65, 128, 521, 1024, 2048].iter() {
+        for _ in 0..20 {
+            let modulus = rng.gen_biguint(bits) | one::<BigUint>();
+            if modulus == one() {
+                continue;
+            }
+            let context = MontgomeryContext::new(&modulus).unwrap();
+            let base = rng.gen_biguint(bits + 10);
+            let exp = rng.gen_biguint(bits);
+            assert_eq!(
+                context.pow(&base, &exp),
+                base.modpow(&exp, &modulus),
+                "modulus={}",
+                modulus
+            );
+        }
+    }
+}
+
+#[test]
+fn pow_edge_cases_test() {
+    let modulus = (one::<BigUint>() << 127u32) - one::<BigUint>();
+    let context = MontgomeryContext::new(&modulus).unwrap();
+    let m1 = &modulus - one::<BigUint>();
+    assert_eq!(context.pow(&5u32.into(), &zero()), one());
+    assert_eq!(context.pow(&zero(), &5u32.into()), zero());
+    assert_eq!(context.pow(&modulus, &5u32.into()), zero());
+    assert_eq!(context.pow(&m1, &2u32.into()), one());
+    assert_eq!(context.pow(&m1, &3u32.into()), m1);
+    assert_eq!(context.pow(&3u32.into(), &m1), one());
+
+    let context = MontgomeryContext::new(&3u32.into()).unwrap();
+    assert_eq!(context.pow(&2u32.into(), &3u32.into()), 2u32.into());
+
+    assert!(MontgomeryContext::new(&one()).is_err());
+    assert!(MontgomeryContext::new(&10u32.into()).is_err());
+}
+
+#[test]
+fn pow_ct_test() {
+    use num_bigint::RandBigInt;
+    let mut rng = rand::thread_rng();
+    for &bits in [2u64, 17, 64, 65, 128, 521, 1024].iter() {
+        for _ in 0..10 {
+            let modulus = rng.gen_biguint(bits) | one::<BigUint>();
+            if modulus == one() {
+                continue;
+            }
+            let context = MontgomeryContext::new(&modulus).unwrap();
+            let base = rng.gen_biguint(bits + 10);
+            let exp = rng.gen_biguint_below(&modulus);
+            assert_eq!(context.pow_ct(&base, &exp), context.pow(&base, &exp));
+        }
+    }
+    let context = MontgomeryContext::new(&101u32.into()).unwrap();
+    assert_eq!(context.pow_ct(&7u32.into(), &zero()), one());
+    assert_eq!(context.pow_ct(&zero(), &7u32.into()), zero());
+}
+
+/// Welch's t statistic for two timing samples, after dropping the slowest 5%
+/// of each class as dudect does to cut scheduler noise.
+#[cfg(test)]
+fn welch_t(a: &mut Vec<f64>, b: &mut Vec<f64>) -> f64 {
+    fn crop_and_stats(x: &mut Vec<f64>) -> (f64, f64, f64) {
+        x.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        x.truncate(x.len() * 95 / 100);
+        let n = x.len() as f64;
+        let mean = x.iter().sum::<f64>() / n;
+        let var = x.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (mean, var, n)
+    }
+    let (mean_a, var_a, n_a) = crop_and_stats(a);
+    let (mean_b, var_b, n_b) = crop_and_stats(b);
+    (mean_a - mean_b) / (var_a / n_a + var_b / n_b).sqrt()
+}
+
+/// dudect-style fixed-vs-random test: times exponentiation with a fixed sparse
+/// exponent against random exponents of the same size, interleaved in random
+/// order, and returns the t statistic of the two timing distributions.
+#[cfg(test)]
+fn timing_t_statistic(pow: impl Fn(&MontgomeryContext, &BigUint, &BigUint) -> BigUint) -> f64 {
+    use num_bigint::RandBigInt;
+    use rand::Rng;
+    use std::time::Instant;
+
+    let mut rng = rand::thread_rng();
+    let bits = 256;
+    let modulus = rng.gen_biguint(bits) | one::<BigUint>() | (one::<BigUint>() << (bits - 1));
+    let context = MontgomeryContext::new(&modulus).unwrap();
+    let base = rng.gen_biguint_below(&modulus);
+    let fixed = (one::<BigUint>() << (bits - 2)) | one::<BigUint>();
+
+    let (mut fixed_times, mut random_times) = (vec![], vec![]);
+    for _ in 0..4000 {
+        let use_fixed = rng.gen::<bool>();
+        let exp = if use_fixed {
+            fixed.clone()
+        } else {
+            rng.gen_biguint(bits - 1) | (one::<BigUint>() << (bits - 2))
+        };
+        let start = Instant::now();
+        let result = pow(&context, &base, &exp);
+        let elapsed = start.elapsed().as_nanos() as f64;
+        std::hint::black_box(result);
+        if use_fixed {
+            fixed_times.push(elapsed);
+        } else {
+            random_times.push(elapsed);
+        }
+    }
+    welch_t(&mut fixed_times, &mut random_times)
+}
+
+#[test]
+#[should_panic(expected = "wider than the modulus")]
+fn pow_ct_wide_exponent_test() {
+    let context = MontgomeryContext::new(&101u32.into()).unwrap();
+    context.pow_ct(&2u32.into(), &(one::<BigUint>() << 64));
+}
+
+#[test]
+fn constant_time_test() {
+    // The sliding-window `pow` skips work for zero bits, which the harness
+    // has to see; `pow_ct` must stay under dudect's threshold for a leak.
+    let variable = timing_t_statistic(|c, b, e| c.pow(b, e));
+    assert!(variable.abs() > 10.0, "variable-time t={}", variable);
+    let constant = timing_t_statistic(|c, b, e| c.pow_ct(b, e));
+    assert!(constant.abs() < 10.0, "constant-time t={}", constant);
+}
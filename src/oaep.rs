@@ -0,0 +1,213 @@
+//! RSAES-OAEP (RFC 8017 §7.1) with MGF1 over the same hash.
+
+use rand::RngCore;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::cipher::{i2osp, os2ip};
+use crate::error::{Error, Result};
+use crate::hash::{mgf1, Hash};
+use crate::keys::{RsaPrivateKey, RsaPublicKey};
+
+/// Bytes of `EM` that are not message: the leading zero, the masked seed and
+/// `lHash`, and the `0x01` separator.
+pub fn overhead(hash: Hash) -> usize {
+    2 * hash.output_len() + 2
+}
+
+fn xor_in_place(data: &mut [u8], mask: &[u8]) {
+    for (byte, m) in data.iter_mut().zip(mask) {
+        *byte ^= m;
+    }
+}
+
+/// RSAES-OAEP-ENCRYPT. Pass an empty `label` when there is none. Fails with
+/// `Error::MessageTooLong` if `msg` is longer than `k - 2 * hLen - 2` bytes.
+pub fn encrypt(public_key: &RsaPublicKey, hash: Hash, label: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+    let mut seed = vec![0; hash.output_len()];
+    rand::thread_rng().fill_bytes(&mut seed);
+    encrypt_with_seed(public_key, hash, label, msg, seed)
+}
+
+/// RSAES-OAEP-ENCRYPT with the given `hLen`-byte seed.
+fn encrypt_with_seed(
+    public_key: &RsaPublicKey,
+    hash: Hash,
+    label: &[u8],
+    msg: &[u8],
+    mut seed: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let k = public_key.size();
+    let h_len = hash.output_len();
+    if k < overhead(hash) || msg.len() > k - overhead(hash) {
+        return Err(Error::MessageTooLong);
+    }
+    // DB = lHash || PS || 0x01 || M
+    let mut db = hash.digest(label);
+    db.resize(k - msg.len() - h_len - 2, 0);
+    db.push(1);
+    db.extend_from_slice(msg);
+
+    let db_mask = mgf1(hash, &seed, db.len());
+    xor_in_place(&mut db, &db_mask);
+    xor_in_place(&mut seed, &mgf1(hash, &db, h_len));
+
+    let mut em = Vec::with_capacity(k);
+    em.push(0);
+    em.extend(seed);
+    em.extend(db);
+    let c = public_key.encrypt_raw(&os2ip(&em))?;
+    Ok(i2osp(&c, k))
+}
+
+/// RSAES-OAEP-DECRYPT. Every decoding failure is reported as the same
+/// `Error::MalformedCiphertext` and the padding is checked without branching
+/// on the decrypted bytes, so failures do not tell which check failed.
+pub fn decrypt(
+    private_key: &RsaPrivateKey,
+    hash: Hash,
+    label: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    let k = private_key.size();
+    let h_len = hash.output_len();
+    if k < overhead(hash) || ciphertext.len() != k {
+        return Err(Error::MalformedCiphertext { offset: 0 });
+    }
+    let em = i2osp(&private_key.decrypt_raw(&os2ip(ciphertext))?, k);
+    let (y, rest) = em.split_first().unwrap();
+    let (masked_seed, masked_db) = rest.split_at(h_len);
+    let mut seed = masked_seed.to_vec();
+    xor_in_place(&mut seed, &mgf1(hash, masked_db, h_len));
+    let mut db = masked_db.to_vec();
+    let db_mask = mgf1(hash, &seed, db.len());
+    xor_in_place(&mut db, &db_mask);
+
+    let mut good = y.ct_eq(&0) & db[..h_len].ct_eq(&hash.digest(label));
+    // Find the 0x01 separator after PS, which must consist of zeros only.
+    let mut found_one = Choice::from(0);
+    let mut msg_index = 0u32;
+    for (i, byte) in db.iter().enumerate().skip(h_len) {
+        let is_one = byte.ct_eq(&1);
+        let is_zero = byte.ct_eq(&0);
+        msg_index.conditional_assign(&(i as u32 + 1), !found_one & is_one);
+        good &= found_one | is_one | is_zero;
+        found_one |= is_one;
+    }
+    good &= found_one;
+    if !bool::from(good) {
+        return Err(Error::MalformedCiphertext { offset: 0 });
+    }
+    Ok(db[msg_index as usize..].to_vec())
+}
+
+#[test]
+fn encrypt_decrypt_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let public_key = private_key.to_public_key();
+    let k = public_key.size();
+    for &hash in [Hash::Sha1, Hash::Sha256, Hash::Sha384, Hash::Sha512].iter() {
+        for &label in [&b""[..], b"label"].iter() {
+            for &len in [0, 1, k - overhead(hash)].iter() {
+                let msg: Vec<u8> = (0..len).map(|i| i as u8).collect();
+                let ciphertext = encrypt(&public_key, hash, label, &msg).unwrap();
+                assert_eq!(ciphertext.len(), k);
+                assert_eq!(
+                    decrypt(&private_key, hash, label, &ciphertext).unwrap(),
+                    msg
+                );
+            }
+        }
+        assert!(matches!(
+            encrypt(&public_key, hash, b"", &vec![0; k - overhead(hash) + 1]),
+            Err(Error::MessageTooLong)
+        ));
+    }
+}
+
+#[test]
+fn openssl_fixtures_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let msg = include_bytes!("../fixtures/oaep/message.txt");
+    let fixtures: [(Hash, &[u8], &[u8]); 5] = [
+        (Hash::Sha1, b"", include_bytes!("../fixtures/oaep/sha1.ct")),
+        (
+            Hash::Sha256,
+            b"",
+            include_bytes!("../fixtures/oaep/sha256.ct"),
+        ),
+        (
+            Hash::Sha384,
+            b"",
+            include_bytes!("../fixtures/oaep/sha384.ct"),
+        ),
+        (
+            Hash::Sha512,
+            b"",
+            include_bytes!("../fixtures/oaep/sha512.ct"),
+        ),
+        (
+            Hash::Sha256,
+            b"encoder",
+            include_bytes!("../fixtures/oaep/sha256_label.ct"),
+        ),
+    ];
+    for (hash, label, ciphertext) in fixtures.iter() {
+        assert_eq!(
+            decrypt(&private_key, *hash, label, ciphertext).unwrap(),
+            msg
+        );
+    }
+}
+
+#[test]
+fn rfc8017_vectors_test() {
+    let private_key =
+        crate::keys::read_private_key(include_bytes!("../fixtures/oaep/rfc8017_example1_key.txt"))
+            .unwrap();
+    let public_key = private_key.to_public_key();
+    let hex = |s: &str| -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    };
+    let vectors = include_str!("../fixtures/oaep/rfc8017_example1.txt");
+    let vectors: Vec<Vec<Vec<u8>>> = vectors
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.split(' ').map(hex).collect())
+        .collect();
+    assert_eq!(vectors.len(), 6);
+    for vector in vectors.iter() {
+        let (msg, seed, encryption) = (&vector[0], &vector[1], &vector[2]);
+        assert_eq!(
+            &decrypt(&private_key, Hash::Sha1, b"", encryption).unwrap(),
+            msg
+        );
+        assert_eq!(
+            &encrypt_with_seed(&public_key, Hash::Sha1, b"", msg, seed.clone()).unwrap(),
+            encryption
+        );
+    }
+}
+
+#[test]
+fn decoding_error_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let public_key = private_key.to_public_key();
+    let ciphertext = encrypt(&public_key, Hash::Sha256, b"label", b"secret").unwrap();
+    assert!(matches!(
+        decrypt(&private_key, Hash::Sha256, b"other", &ciphertext),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+    assert!(matches!(
+        decrypt(&private_key, Hash::Sha1, b"label", &ciphertext),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+    let mut corrupted = ciphertext;
+    corrupted[100] ^= 0x80;
+    assert!(matches!(
+        decrypt(&private_key, Hash::Sha256, b"label", &corrupted),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+}
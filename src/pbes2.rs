@@ -0,0 +1,567 @@
+//! Passphrase-encrypted PKCS#8 private keys (`EncryptedPrivateKeyInfo`, RFC 5208)
+//! with PBES2 (RFC 8018 §6.2):
+//!
+//! ```text
+//! EncryptedPrivateKeyInfo ::= SEQUENCE {
+//!     encryptionAlgorithm AlgorithmIdentifier, -- id-PBES2 --
+//!     encryptedData OCTET STRING }
+//! PBES2-params ::= SEQUENCE {
+//!     keyDerivationFunc AlgorithmIdentifier, encryptionScheme AlgorithmIdentifier }
+//! ```
+//!
+//! The key derivation function is PBKDF2 with HMAC-SHA-1/256/384/512 or scrypt
+//! (RFC 7914 §7), the encryption scheme AES-256-CBC with PKCS#7 padding or
+//! AES-256-GCM (RFC 5084) with a 16-byte tag. The encrypted data is a DER
+//! `PrivateKeyInfo`.
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit};
+use rand::RngCore;
+
+use crate::der::{self, Reader, INTEGER, NULL, OBJECT_IDENTIFIER, OCTET_STRING, SEQUENCE};
+use crate::error::{Error, Result};
+use crate::keys::RsaPrivateKey;
+
+/// 1.2.840.113549.1.5.13
+const PBES2: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+/// 1.2.840.113549.1.5.12
+const PBKDF2: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c];
+/// 1.3.6.1.4.1.11591.4.11
+const SCRYPT: [u8; 9] = [0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x04, 0x0b];
+/// 1.2.840.113549.2.7, .9, .10 and .11; `hmacWithSHA1` is the default PRF.
+const HMAC_WITH_SHA1: [u8; 8] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x07];
+const HMAC_WITH_SHA256: [u8; 8] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09];
+const HMAC_WITH_SHA384: [u8; 8] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0a];
+const HMAC_WITH_SHA512: [u8; 8] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0b];
+/// 2.16.840.1.101.3.4.1.42 and .46
+const AES_256_CBC: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+const AES_256_GCM: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2e];
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const CBC_IV_LEN: usize = 16;
+const GCM_NONCE_LEN: usize = 12;
+const GCM_TAG_LEN: u32 = 16;
+
+/// Decryption refuses parameters above these, so a crafted key file cannot
+/// make it run for hours or allocate gigabytes: 10^7 PBKDF2 iterations, and
+/// 1 GiB of scrypt memory `128 * r * N` times the parallelism `p`.
+const MAX_ITERATIONS: u32 = 10_000_000;
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+
+/// Password-based key derivation function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kdf {
+    /// PBKDF2 with HMAC over `hash`.
+    Pbkdf2 { hash: PbkdfHash, iterations: u32 },
+    /// scrypt with cost `N = 2^log_n`, block size `r` and parallelism `p`.
+    Scrypt { log_n: u8, r: u32, p: u32 },
+}
+
+impl Kdf {
+    /// PBKDF2-HMAC-SHA256 with 600 000 iterations, the OWASP recommendation.
+    pub const PBKDF2: Kdf = Kdf::Pbkdf2 {
+        hash: PbkdfHash::Sha256,
+        iterations: 600_000,
+    };
+    /// `N = 2^14, r = 8, p = 1`, the largest cost OpenSSL reads with its
+    /// default 32 MiB scrypt memory limit.
+    pub const SCRYPT: Kdf = Kdf::Scrypt {
+        log_n: 14,
+        r: 8,
+        p: 1,
+    };
+
+    fn derive(self, passphrase: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+        let mut key = [0; KEY_LEN];
+        match self {
+            Kdf::Pbkdf2 { hash, iterations } => match hash {
+                PbkdfHash::Sha1 => {
+                    pbkdf2::pbkdf2_hmac::<sha1::Sha1>(passphrase, salt, iterations, &mut key)
+                }
+                PbkdfHash::Sha256 => {
+                    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase, salt, iterations, &mut key)
+                }
+                PbkdfHash::Sha384 => {
+                    pbkdf2::pbkdf2_hmac::<sha2::Sha384>(passphrase, salt, iterations, &mut key)
+                }
+                PbkdfHash::Sha512 => {
+                    pbkdf2::pbkdf2_hmac::<sha2::Sha512>(passphrase, salt, iterations, &mut key)
+                }
+            },
+            Kdf::Scrypt { log_n, r, p } => {
+                let params = scrypt::Params::new(log_n, r, p, KEY_LEN)
+                    .map_err(|_| Error::InvalidKey("invalid scrypt parameters"))?;
+                scrypt::scrypt(passphrase, salt, &params, &mut key)
+                    .map_err(|_| Error::InvalidKey("invalid scrypt parameters"))?;
+            }
+        }
+        Ok(key)
+    }
+
+    fn check_cost(self) -> Result<()> {
+        let too_expensive = match self {
+            Kdf::Pbkdf2 { iterations, .. } => iterations > MAX_ITERATIONS,
+            Kdf::Scrypt { log_n, r, p } => {
+                log_n >= 64
+                    || 128u64
+                        .saturating_mul(r as u64)
+                        .saturating_mul(1 << log_n)
+                        .saturating_mul(p as u64)
+                        > MAX_SCRYPT_MEMORY
+            }
+        };
+        if too_expensive {
+            return Err(Error::InvalidKey(
+                "the key derivation parameters are too expensive",
+            ));
+        }
+        Ok(())
+    }
+
+    fn encode(self, salt: &[u8]) -> Vec<u8> {
+        let mut params = vec![];
+        der::push_tlv(&mut params, OCTET_STRING, salt);
+        let oid = match self {
+            Kdf::Pbkdf2 { hash, iterations } => {
+                der::push_integer(&mut params, &iterations.into());
+                // DER leaves out the default `hmacWithSHA1`.
+                if hash != PbkdfHash::Sha1 {
+                    let mut prf = vec![];
+                    der::push_tlv(&mut prf, OBJECT_IDENTIFIER, hash.oid());
+                    der::push_tlv(&mut prf, NULL, &[]);
+                    der::push_tlv(&mut params, SEQUENCE, &prf);
+                }
+                &PBKDF2
+            }
+            Kdf::Scrypt { log_n, r, p } => {
+                der::push_integer(&mut params, &(1u64 << log_n).into());
+                der::push_integer(&mut params, &r.into());
+                der::push_integer(&mut params, &p.into());
+                &SCRYPT
+            }
+        };
+        algorithm(oid, SEQUENCE, &params)
+    }
+
+    /// Salt and function of a `keyDerivationFunc`.
+    fn decode(reader: &mut Reader) -> Result<(Vec<u8>, Kdf)> {
+        let mut algorithm = Reader {
+            data: reader.read(SEQUENCE)?,
+        };
+        let oid = algorithm.read(OBJECT_IDENTIFIER)?;
+        let mut params = Reader {
+            data: algorithm.read(SEQUENCE)?,
+        };
+        algorithm.finish()?;
+        let salt = params.read(OCTET_STRING)?.to_vec();
+        let kdf = if oid == PBKDF2 {
+            let iterations = params.read_u32()?;
+            read_key_len(&mut params)?;
+            if iterations == 0 {
+                return Err(Error::InvalidKey("PBKDF2 needs at least one iteration"));
+            }
+            let hash = match params.peek_tag() {
+                Some(SEQUENCE) => {
+                    let mut prf = Reader {
+                        data: params.read(SEQUENCE)?,
+                    };
+                    let hash = PbkdfHash::from_oid(prf.read(OBJECT_IDENTIFIER)?)?;
+                    if !prf.data.is_empty() {
+                        prf.read(NULL)?;
+                    }
+                    prf.finish()?;
+                    hash
+                }
+                _ => PbkdfHash::Sha1,
+            };
+            Kdf::Pbkdf2 { hash, iterations }
+        } else if oid == SCRYPT {
+            let n = params.read_u32()?;
+            let r = params.read_u32()?;
+            let p = params.read_u32()?;
+            read_key_len(&mut params)?;
+            if n < 2 || !n.is_power_of_two() {
+                return Err(Error::InvalidKey("scrypt cost must be a power of two"));
+            }
+            Kdf::Scrypt {
+                log_n: n.trailing_zeros() as u8,
+                r,
+                p,
+            }
+        } else {
+            return Err(Error::InvalidKey(
+                "the key derivation function is not supported",
+            ));
+        };
+        params.finish()?;
+        Ok((salt, kdf))
+    }
+}
+
+impl std::str::FromStr for Kdf {
+    type Err = ();
+
+    /// `pbkdf2` or `scrypt` with the default parameters.
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "pbkdf2" => Ok(Kdf::PBKDF2),
+            "scrypt" => Ok(Kdf::SCRYPT),
+            _ => Err(()),
+        }
+    }
+}
+
+/// PRF hash of PBKDF2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PbkdfHash {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl PbkdfHash {
+    fn oid(self) -> &'static [u8] {
+        match self {
+            PbkdfHash::Sha1 => &HMAC_WITH_SHA1,
+            PbkdfHash::Sha256 => &HMAC_WITH_SHA256,
+            PbkdfHash::Sha384 => &HMAC_WITH_SHA384,
+            PbkdfHash::Sha512 => &HMAC_WITH_SHA512,
+        }
+    }
+
+    fn from_oid(oid: &[u8]) -> Result<Self> {
+        [
+            PbkdfHash::Sha1,
+            PbkdfHash::Sha256,
+            PbkdfHash::Sha384,
+            PbkdfHash::Sha512,
+        ]
+        .iter()
+        .copied()
+        .find(|hash| hash.oid() == oid)
+        .ok_or(Error::InvalidKey("the PBKDF2 hash is not supported"))
+    }
+}
+
+/// Cipher that encrypts the `PrivateKeyInfo`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Cbc,
+    Aes256Gcm,
+}
+
+impl std::str::FromStr for Cipher {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "aes256cbc" => Ok(Cipher::Aes256Cbc),
+            "aes256gcm" => Ok(Cipher::Aes256Gcm),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Cipher {
+    fn iv_len(self) -> usize {
+        match self {
+            Cipher::Aes256Cbc => CBC_IV_LEN,
+            Cipher::Aes256Gcm => GCM_NONCE_LEN,
+        }
+    }
+
+    fn encode(self, iv: &[u8]) -> Vec<u8> {
+        match self {
+            Cipher::Aes256Cbc => algorithm(&AES_256_CBC, OCTET_STRING, iv),
+            Cipher::Aes256Gcm => {
+                // GCMParameters ::= SEQUENCE { aes-nonce OCTET STRING, aes-ICVlen INTEGER DEFAULT 12 }
+                let mut params = vec![];
+                der::push_tlv(&mut params, OCTET_STRING, iv);
+                der::push_integer(&mut params, &GCM_TAG_LEN.into());
+                algorithm(&AES_256_GCM, SEQUENCE, &params)
+            }
+        }
+    }
+
+    /// IV and cipher of an `encryptionScheme`.
+    fn decode(reader: &mut Reader) -> Result<(Vec<u8>, Cipher)> {
+        let mut algorithm = Reader {
+            data: reader.read(SEQUENCE)?,
+        };
+        let oid = algorithm.read(OBJECT_IDENTIFIER)?;
+        let (iv, cipher) = if oid == AES_256_CBC {
+            (algorithm.read(OCTET_STRING)?, Cipher::Aes256Cbc)
+        } else if oid == AES_256_GCM {
+            let mut params = Reader {
+                data: algorithm.read(SEQUENCE)?,
+            };
+            let nonce = params.read(OCTET_STRING)?;
+            let tag_len = match params.peek_tag() {
+                Some(INTEGER) => params.read_u32()?,
+                _ => 12,
+            };
+            params.finish()?;
+            if tag_len != GCM_TAG_LEN {
+                return Err(Error::InvalidKey("only 16-byte GCM tags are supported"));
+            }
+            (nonce, Cipher::Aes256Gcm)
+        } else {
+            return Err(Error::InvalidKey(
+                "the key encryption cipher is not supported",
+            ));
+        };
+        algorithm.finish()?;
+        if iv.len() != cipher.iv_len() {
+            return Err(Error::MalformedKey);
+        }
+        Ok((iv.to_vec(), cipher))
+    }
+
+    fn encrypt(self, key: &[u8; KEY_LEN], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        match self {
+            Cipher::Aes256Cbc => cbc::Encryptor::<aes::Aes256>::new(key.into(), iv.into())
+                .encrypt_padded_vec_mut::<Pkcs7>(plaintext),
+            Cipher::Aes256Gcm => Aes256Gcm::new(key.into())
+                .encrypt(iv.into(), plaintext)
+                .unwrap(),
+        }
+    }
+
+    fn decrypt(self, key: &[u8; KEY_LEN], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Cipher::Aes256Cbc => cbc::Decryptor::<aes::Aes256>::new(key.into(), iv.into())
+                .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+                .map_err(|_| Error::WrongPassphrase),
+            Cipher::Aes256Gcm => Aes256Gcm::new(key.into())
+                .decrypt(iv.into(), ciphertext)
+                .map_err(|_| Error::WrongPassphrase),
+        }
+    }
+}
+
+/// `AlgorithmIdentifier` with the parameters element `tag`, `params`.
+fn algorithm(oid: &[u8], tag: u8, params: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    der::push_tlv(&mut body, OBJECT_IDENTIFIER, oid);
+    der::push_tlv(&mut body, tag, params);
+    let mut out = vec![];
+    der::push_tlv(&mut out, SEQUENCE, &body);
+    out
+}
+
+/// Optional `keyLength`, which must match AES-256 when present.
+fn read_key_len(params: &mut Reader) -> Result<()> {
+    if params.peek_tag() == Some(INTEGER) && params.read_u32()? != KEY_LEN as u32 {
+        return Err(Error::InvalidKey("the key length must be 32 bytes"));
+    }
+    Ok(())
+}
+
+/// `EncryptedPrivateKeyInfo` of `private_key` under `passphrase`, with a
+/// random salt and IV.
+pub fn encrypt(
+    private_key: &RsaPrivateKey,
+    passphrase: &[u8],
+    kdf: Kdf,
+    cipher: Cipher,
+) -> Result<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let mut salt = [0; SALT_LEN];
+    rng.fill_bytes(&mut salt);
+    let mut iv = vec![0; cipher.iv_len()];
+    rng.fill_bytes(&mut iv);
+
+    let key = kdf.derive(passphrase, &salt)?;
+    let encrypted = cipher.encrypt(&key, &iv, &der::encode_pkcs8(private_key));
+
+    let mut params = kdf.encode(&salt);
+    params.extend(cipher.encode(&iv));
+    let mut body = algorithm(&PBES2, SEQUENCE, &params);
+    der::push_tlv(&mut body, OCTET_STRING, &encrypted);
+    let mut out = vec![];
+    der::push_tlv(&mut out, SEQUENCE, &body);
+    Ok(out)
+}
+
+/// A parsed `EncryptedPrivateKeyInfo`, waiting for the passphrase.
+#[derive(Clone, Debug)]
+pub struct EncryptedPrivateKey {
+    kdf: Kdf,
+    salt: Vec<u8>,
+    cipher: Cipher,
+    iv: Vec<u8>,
+    encrypted: Vec<u8>,
+}
+
+impl EncryptedPrivateKey {
+    /// Fails with `Error::MalformedKey` unless `der` is an `EncryptedPrivateKeyInfo`
+    /// and with `Error::InvalidKey` if it uses an unsupported or too
+    /// expensive scheme, so callers can check before asking for a passphrase.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let mut reader = der::read_sequence(der)?;
+        let mut algorithm = Reader {
+            data: reader.read(SEQUENCE)?,
+        };
+        let oid = algorithm.read(OBJECT_IDENTIFIER)?;
+        if oid != PBES2 {
+            // Other PKCS#5 schemes are PBES1; any other algorithm means this
+            // is not an encrypted key at all.
+            if oid.starts_with(&PBES2[..PBES2.len() - 1]) {
+                return Err(Error::InvalidKey(
+                    "only PBES2 encrypted private keys are supported",
+                ));
+            }
+            return Err(Error::MalformedKey);
+        }
+        let mut params = Reader {
+            data: algorithm.read(SEQUENCE)?,
+        };
+        algorithm.finish()?;
+        let (salt, kdf) = Kdf::decode(&mut params)?;
+        let (iv, cipher) = Cipher::decode(&mut params)?;
+        params.finish()?;
+        let encrypted = reader.read(OCTET_STRING)?.to_vec();
+        reader.finish()?;
+        kdf.check_cost()?;
+        Ok(EncryptedPrivateKey {
+            kdf,
+            salt,
+            cipher,
+            iv,
+            encrypted,
+        })
+    }
+
+    /// Fails with `Error::WrongPassphrase` if the data does not decrypt to a
+    /// private key, which a wrong passphrase almost always causes.
+    pub fn decrypt(&self, passphrase: &[u8]) -> Result<RsaPrivateKey> {
+        let key = self.kdf.derive(passphrase, &self.salt)?;
+        let der = self.cipher.decrypt(&key, &self.iv, &self.encrypted)?;
+        der::decode_pkcs8(&der).map_err(|err| match err {
+            // CBC padding that checks out by chance.
+            Error::MalformedKey => Error::WrongPassphrase,
+            err => err,
+        })
+    }
+}
+
+#[test]
+fn openssl_fixtures_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let fixtures: [(&[u8], Kdf); 3] = [
+        (
+            include_bytes!("../fixtures/pkcs8/pbkdf2_sha256_aes256cbc.pem"),
+            Kdf::Pbkdf2 {
+                hash: PbkdfHash::Sha256,
+                iterations: 2048,
+            },
+        ),
+        (
+            include_bytes!("../fixtures/pkcs8/pbkdf2_sha1_aes256cbc.pem"),
+            Kdf::Pbkdf2 {
+                hash: PbkdfHash::Sha1,
+                iterations: 2048,
+            },
+        ),
+        (
+            include_bytes!("../fixtures/pkcs8/scrypt_aes256cbc.pem"),
+            Kdf::Scrypt {
+                log_n: 10,
+                r: 8,
+                p: 1,
+            },
+        ),
+    ];
+    for (pem, kdf) in fixtures.iter() {
+        let (label, der) = crate::pem::decode(pem).unwrap();
+        assert_eq!(label, crate::pem::ENCRYPTED_PRIVATE_KEY);
+        let encrypted = EncryptedPrivateKey::from_der(&der).unwrap();
+        assert_eq!(encrypted.kdf, *kdf);
+        assert_eq!(encrypted.cipher, Cipher::Aes256Cbc);
+        assert_eq!(encrypted.decrypt(b"encoder").unwrap(), private_key);
+        assert!(matches!(
+            encrypted.decrypt(b"encodeR"),
+            Err(Error::WrongPassphrase)
+        ));
+        // Writing the parameters back gives the same DER.
+        let mut params = kdf.encode(&encrypted.salt);
+        params.extend(encrypted.cipher.encode(&encrypted.iv));
+        let pbes2 = algorithm(&PBES2, SEQUENCE, &params);
+        assert_eq!(&der[4..4 + pbes2.len()], &pbes2[..]);
+    }
+}
+
+#[test]
+fn round_trip_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let cheap_pbkdf2 = Kdf::Pbkdf2 {
+        hash: PbkdfHash::Sha512,
+        iterations: 1000,
+    };
+    let cheap_scrypt = Kdf::Scrypt {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+    for &kdf in [cheap_pbkdf2, cheap_scrypt].iter() {
+        for &cipher in [Cipher::Aes256Cbc, Cipher::Aes256Gcm].iter() {
+            let der = encrypt(&private_key, "пароль".as_bytes(), kdf, cipher).unwrap();
+            let encrypted = EncryptedPrivateKey::from_der(&der).unwrap();
+            assert_eq!((encrypted.kdf, encrypted.cipher), (kdf, cipher));
+            assert_eq!(encrypted.decrypt("пароль".as_bytes()).unwrap(), private_key);
+            assert!(matches!(
+                encrypted.decrypt(b"password"),
+                Err(Error::WrongPassphrase)
+            ));
+        }
+    }
+}
+
+#[test]
+fn unsupported_test() {
+    let private_key = crate::test_keys::rsa1024();
+    assert!(matches!(
+        EncryptedPrivateKey::from_der(&der::encode_pkcs8(&private_key)),
+        Err(Error::MalformedKey)
+    ));
+    assert!(matches!(
+        EncryptedPrivateKey::from_der(&der::encode_spki(&private_key.to_public_key())),
+        Err(Error::MalformedKey)
+    ));
+    // pbeWithSHA1AndDES-CBC, 1.2.840.113549.1.5.10.
+    let pbe_with_sha1_and_des = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0a];
+    let mut pbes1 = algorithm(&pbe_with_sha1_and_des, SEQUENCE, &[]);
+    der::push_tlv(&mut pbes1, OCTET_STRING, &[0; 16]);
+    let mut der = vec![];
+    der::push_tlv(&mut der, SEQUENCE, &pbes1);
+    assert!(matches!(
+        EncryptedPrivateKey::from_der(&der),
+        Err(Error::InvalidKey(_))
+    ));
+    let expensive = [
+        Kdf::Pbkdf2 {
+            hash: PbkdfHash::Sha256,
+            iterations: MAX_ITERATIONS + 1,
+        },
+        Kdf::Scrypt {
+            log_n: 20,
+            r: 16,
+            p: 1,
+        },
+    ];
+    for kdf in expensive.iter() {
+        let mut params = kdf.encode(&[0; SALT_LEN]);
+        params.extend(Cipher::Aes256Cbc.encode(&[0; CBC_IV_LEN]));
+        let mut body = algorithm(&PBES2, SEQUENCE, &params);
+        der::push_tlv(&mut body, OCTET_STRING, &[0; 16]);
+        let mut der = vec![];
+        der::push_tlv(&mut der, SEQUENCE, &body);
+        assert!(matches!(
+            EncryptedPrivateKey::from_der(&der),
+            Err(Error::InvalidKey(_))
+        ));
+    }
+}
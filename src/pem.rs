@@ -0,0 +1,108 @@
+//! PEM armour (RFC 7468): base64 DER between `-----BEGIN <label>-----` and
+//! `-----END <label>-----` lines.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::error::{Error, Result};
+
+pub const RSA_PRIVATE_KEY: &str = "RSA PRIVATE KEY";
+pub const PRIVATE_KEY: &str = "PRIVATE KEY";
+pub const ENCRYPTED_PRIVATE_KEY: &str = "ENCRYPTED PRIVATE KEY";
+pub const RSA_PUBLIC_KEY: &str = "RSA PUBLIC KEY";
+pub const PUBLIC_KEY: &str = "PUBLIC KEY";
+
+const BEGIN: &str = "-----BEGIN ";
+const END: &str = "-----END ";
+const DASHES: &str = "-----";
+
+/// Base64 characters per line, as OpenSSL writes them.
+const LINE_LEN: usize = 64;
+
+pub fn encode(label: &str, der: &[u8]) -> String {
+    let body = STANDARD.encode(der);
+    let mut pem = format!("{}{}{}\n", BEGIN, label, DASHES);
+    for line in body.as_bytes().chunks(LINE_LEN) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("{}{}{}\n", END, label, DASHES));
+    pem
+}
+
+/// Whether `text` holds a PEM block rather than another key file layout.
+pub fn is_pem(text: &[u8]) -> bool {
+    text.windows(BEGIN.len())
+        .any(|window| window == BEGIN.as_bytes())
+}
+
+/// Label and contents of the first PEM block in `text`. Text before the
+/// block is skipped, as OpenSSL does; the `END` label must match `BEGIN`.
+pub fn decode(text: &[u8]) -> Result<(String, Vec<u8>)> {
+    let text = std::str::from_utf8(text).map_err(|_| Error::MalformedKey)?;
+    let start = text.find(BEGIN).ok_or(Error::MalformedKey)? + BEGIN.len();
+    let (label, rest) = text[start..]
+        .split_once(DASHES)
+        .ok_or(Error::MalformedKey)?;
+    let end = format!("{}{}{}", END, label, DASHES);
+    let (body, _) = rest.split_once(&end).ok_or(Error::MalformedKey)?;
+    // Legacy OpenSSL encryption puts `Proc-Type` and `DEK-Info` headers here.
+    if body.contains(':') {
+        return Err(Error::InvalidKey(
+            "PEM headers such as legacy encryption are not supported",
+        ));
+    }
+    let body: String = body.split_whitespace().collect();
+    let der = STANDARD.decode(body).map_err(|_| Error::MalformedKey)?;
+    Ok((label.to_string(), der))
+}
+
+#[test]
+fn openssl_fixtures_test() {
+    let fixtures: [(&str, &[u8]); 4] = [
+        (
+            RSA_PRIVATE_KEY,
+            include_bytes!("../fixtures/pem/rsa1024_pkcs1.pem"),
+        ),
+        (
+            PRIVATE_KEY,
+            include_bytes!("../fixtures/pem/rsa1024_pkcs8.pem"),
+        ),
+        (
+            RSA_PUBLIC_KEY,
+            include_bytes!("../fixtures/pem/rsa1024_pkcs1_public.pem"),
+        ),
+        (
+            PUBLIC_KEY,
+            include_bytes!("../fixtures/pem/rsa1024_spki.pem"),
+        ),
+    ];
+    for (expected_label, pem) in fixtures.iter() {
+        assert!(is_pem(pem));
+        let (label, der) = decode(pem).unwrap();
+        assert_eq!(label, *expected_label);
+        assert_eq!(encode(&label, &der).as_bytes(), *pem);
+    }
+}
+
+#[test]
+fn malformed_test() {
+    let pem = encode(PUBLIC_KEY, b"\x30\x00");
+    let crlf = format!("comment\r\n{}", pem.replace('\n', "\r\n"));
+    assert_eq!(
+        decode(crlf.as_bytes()).unwrap(),
+        (PUBLIC_KEY.to_string(), b"\x30\x00".to_vec())
+    );
+    assert!(!is_pem(b"3233 17"));
+    let mismatched = pem.replace("END PUBLIC", "END PRIVATE");
+    let truncated = &pem[..pem.len() - 10];
+    let bad_base64 = pem.replace("MAA=", "M*A=");
+    let encrypted = pem.replace("MAA=", "Proc-Type: 4,ENCRYPTED\n\nMAA=");
+    for text in [&mismatched[..], truncated, &bad_base64].iter() {
+        assert!(matches!(decode(text.as_bytes()), Err(Error::MalformedKey)));
+    }
+    assert!(matches!(
+        decode(encrypted.as_bytes()),
+        Err(Error::InvalidKey(_))
+    ));
+}
@@ -0,0 +1,302 @@
+//! RSAES-PKCS1-v1_5 (RFC 8017 §7.2) and RSASSA-PKCS1-v1_5 (§8.2).
+
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use subtle::{
+    Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater, ConstantTimeLess,
+};
+
+use crate::cipher::{i2osp, os2ip};
+use crate::error::{Error, Result};
+use crate::hash::Hash;
+use crate::keys::{RsaPrivateKey, RsaPublicKey};
+
+/// Bytes of `EM = 0x00 || 0x02 || PS || 0x00 || M` that are not message,
+/// with the minimum 8 bytes of `PS`.
+pub const OVERHEAD: usize = 11;
+
+/// Number of 16-bit candidates drawn for the synthetic message length.
+const LENGTH_CANDIDATES: usize = 128;
+
+/// RSAES-PKCS1-V1_5-ENCRYPT. Fails with `Error::MessageTooLong` if `msg` is
+/// longer than `k - 11` bytes.
+pub fn encrypt(public_key: &RsaPublicKey, msg: &[u8]) -> Result<Vec<u8>> {
+    let k = public_key.size();
+    if k < OVERHEAD || msg.len() > k - OVERHEAD {
+        return Err(Error::MessageTooLong);
+    }
+    let mut rng = rand::thread_rng();
+    let mut em = Vec::with_capacity(k);
+    em.extend_from_slice(&[0, 2]);
+    for _ in 0..k - msg.len() - 3 {
+        em.push(rng.gen_range(1..=255));
+    }
+    em.push(0);
+    em.extend_from_slice(msg);
+    let c = public_key.encrypt_raw(&os2ip(&em))?;
+    Ok(i2osp(&c, k))
+}
+
+/// `PRF(kdk, label, len)`: `len` bytes of `HMAC-SHA256(kdk, I2OSP(i, 2) ||
+/// label || I2OSP(8 * len, 2))` for `i = 0, 1, ...`.
+fn prf(kdk: &[u8], label: &[u8], len: usize) -> Vec<u8> {
+    let bit_len = ((len * 8) as u16).to_be_bytes();
+    let mut out = Vec::with_capacity(len + 32);
+    let mut i: u16 = 0;
+    while out.len() < len {
+        let mut mac = Hmac::<Sha256>::new_from_slice(kdk).unwrap();
+        mac.update(&i.to_be_bytes());
+        mac.update(label);
+        mac.update(&bit_len);
+        out.extend_from_slice(&mac.finalize().into_bytes());
+        i += 1;
+    }
+    out.truncate(len);
+    out
+}
+
+/// The message returned instead of an error when the padding is invalid:
+/// `k` pseudo-random bytes and the index where the synthetic message starts
+/// in them. Both are derived from the private exponent and the ciphertext, so
+/// the same ciphertext always decrypts to the same result.
+fn synthetic_message(private_key: &RsaPrivateKey, ciphertext: &[u8]) -> (Vec<u8>, u32) {
+    let k = private_key.size();
+    let d_hash = Sha256::digest(i2osp(private_key.d(), k));
+    let mut mac = Hmac::<Sha256>::new_from_slice(&d_hash).unwrap();
+    mac.update(ciphertext);
+    let kdk = mac.finalize().into_bytes();
+
+    let message = prf(&kdk, b"message", k);
+    let lengths = prf(&kdk, b"length", 2 * LENGTH_CANDIDATES);
+
+    // Longest message that fits next to the 0x00 0x02 prefix and 8 bytes of PS.
+    let max_len = (k - 2 - 8) as u16;
+    let mut mask = max_len;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    let mut len = 0u16;
+    for pair in lengths.chunks(2) {
+        let candidate = u16::from_be_bytes([pair[0], pair[1]]) & mask;
+        len.conditional_assign(&candidate, candidate.ct_lt(&max_len));
+    }
+    (message, (k - len as usize) as u32)
+}
+
+/// RSAES-PKCS1-V1_5-DECRYPT with implicit rejection: a ciphertext with
+/// invalid padding does not produce an error but decrypts to a synthetic
+/// message, so callers cannot become a Bleichenbacher padding oracle. The
+/// padding is checked without branching on the decrypted bytes.
+pub fn decrypt(private_key: &RsaPrivateKey, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let k = private_key.size();
+    if k < OVERHEAD || ciphertext.len() != k {
+        return Err(Error::MalformedCiphertext { offset: 0 });
+    }
+    let em = i2osp(&private_key.decrypt_raw(&os2ip(ciphertext))?, k);
+    let (synthetic, synthetic_index) = synthetic_message(private_key, ciphertext);
+
+    let mut good = em[0].ct_eq(&0) & em[1].ct_eq(&2);
+    let mut found_zero = Choice::from(0);
+    let mut zero_index = 0u32;
+    for (i, byte) in em.iter().enumerate().skip(2) {
+        let is_zero = byte.ct_eq(&0);
+        zero_index.conditional_assign(&(i as u32), !found_zero & is_zero);
+        found_zero |= is_zero;
+    }
+    // PS starts at index 2 and must be at least 8 bytes long.
+    good &= zero_index.ct_gt(&9);
+
+    let index = u32::conditional_select(&synthetic_index, &(zero_index + 1), good) as usize;
+    Ok(em[index..]
+        .iter()
+        .zip(synthetic[index..].iter())
+        .map(|(real, fake)| u8::conditional_select(fake, real, good))
+        .collect())
+}
+
+/// EMSA-PKCS1-v1_5: `0x00 || 0x01 || 0xff..ff || 0x00 || DigestInfo` of
+/// `em_len` bytes.
+fn emsa_encode(hash: Hash, msg: &[u8], em_len: usize) -> Result<Vec<u8>> {
+    let mut t = hash.digest_info_prefix().to_vec();
+    t.extend(hash.digest(msg));
+    if em_len < t.len() + OVERHEAD {
+        return Err(Error::MessageTooLong);
+    }
+    let mut em = vec![0, 1];
+    em.resize(em_len - t.len() - 1, 0xff);
+    em.push(0);
+    em.extend(t);
+    Ok(em)
+}
+
+/// RSASSA-PKCS1-V1_5-SIGN. Fails with `Error::MessageTooLong` if the key is
+/// too small for the `DigestInfo` of `hash`.
+pub fn sign(private_key: &RsaPrivateKey, hash: Hash, msg: &[u8]) -> Result<Vec<u8>> {
+    let k = private_key.size();
+    let em = emsa_encode(hash, msg, k)?;
+    let s = private_key.sign_raw(&os2ip(&em))?;
+    Ok(i2osp(&s, k))
+}
+
+/// RSASSA-PKCS1-V1_5-VERIFY. The expected block is re-encoded from `msg` and
+/// compared with the whole recovered block instead of parsing the latter, so
+/// trailing garbage or alternative `DigestInfo` encodings cannot pass.
+pub fn verify(public_key: &RsaPublicKey, hash: Hash, msg: &[u8], signature: &[u8]) -> Result<()> {
+    let k = public_key.size();
+    if signature.len() != k {
+        return Err(Error::InvalidSignature);
+    }
+    let m = public_key
+        .encrypt_raw(&os2ip(signature))
+        .map_err(|_| Error::InvalidSignature)?;
+    let expected = emsa_encode(hash, msg, k).map_err(|_| Error::InvalidSignature)?;
+    if !bool::from(i2osp(&m, k).ct_eq(&expected)) {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(())
+}
+
+#[test]
+fn encrypt_decrypt_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let public_key = private_key.to_public_key();
+    let k = public_key.size();
+    for &len in [0, 1, 16, k - OVERHEAD].iter() {
+        let msg: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let ciphertext = encrypt(&public_key, &msg).unwrap();
+        assert_eq!(ciphertext.len(), k);
+        assert_ne!(encrypt(&public_key, &msg).unwrap(), ciphertext);
+        assert_eq!(decrypt(&private_key, &ciphertext).unwrap(), msg);
+    }
+    assert!(matches!(
+        encrypt(&public_key, &vec![0; k - OVERHEAD + 1]),
+        Err(Error::MessageTooLong)
+    ));
+    assert!(matches!(
+        decrypt(&private_key, &[0; 10]),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+}
+
+#[test]
+fn openssl_fixtures_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let fixtures: [(&[u8], &[u8]); 3] = [
+        (
+            include_bytes!("../fixtures/pkcs1v15/valid.ct"),
+            include_bytes!("../fixtures/pkcs1v15/valid.msg"),
+        ),
+        (
+            include_bytes!("../fixtures/pkcs1v15/no_separator.ct"),
+            include_bytes!("../fixtures/pkcs1v15/no_separator.msg"),
+        ),
+        (
+            include_bytes!("../fixtures/pkcs1v15/wrong_block_type.ct"),
+            include_bytes!("../fixtures/pkcs1v15/wrong_block_type.msg"),
+        ),
+    ];
+    for (ciphertext, msg) in fixtures.iter() {
+        assert_eq!(decrypt(&private_key, ciphertext).unwrap(), *msg);
+    }
+}
+
+#[test]
+fn implicit_rejection_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let public_key = private_key.to_public_key();
+    let k = public_key.size();
+    let mut ciphertext = encrypt(&public_key, b"attack at dawn").unwrap();
+    ciphertext[k - 1] ^= 1;
+    let first = decrypt(&private_key, &ciphertext).unwrap();
+    assert_eq!(decrypt(&private_key, &ciphertext).unwrap(), first);
+    assert_ne!(first, b"attack at dawn");
+    assert!(first.len() <= k - OVERHEAD);
+}
+
+#[test]
+fn sign_verify_test() {
+    let private_key = crate::test_keys::rsa1024();
+    let public_key = private_key.to_public_key();
+    let msg = b"Hello, world!";
+    for &hash in [Hash::Sha1, Hash::Sha256, Hash::Sha384, Hash::Sha512].iter() {
+        let signature = sign(&private_key, hash, msg).unwrap();
+        assert_eq!(signature, sign(&private_key, hash, msg).unwrap());
+        verify(&public_key, hash, msg, &signature).unwrap();
+        assert!(matches!(
+            verify(&public_key, hash, b"Hello, world?", &signature),
+            Err(Error::InvalidSignature)
+        ));
+    }
+    let signature = sign(&private_key, Hash::Sha256, msg).unwrap();
+    assert!(matches!(
+        verify(&public_key, Hash::Sha384, msg, &signature),
+        Err(Error::InvalidSignature)
+    ));
+    assert!(matches!(
+        verify(&public_key, Hash::Sha256, msg, &signature[1..]),
+        Err(Error::InvalidSignature)
+    ));
+}
+
+#[test]
+fn openssl_signature_fixtures_test() {
+    let public_key = crate::test_keys::rsa2048().to_public_key();
+    let msg = include_bytes!("../fixtures/pkcs1v15/message.txt");
+    let fixtures: [(Hash, &[u8]); 3] = [
+        (
+            Hash::Sha256,
+            include_bytes!("../fixtures/pkcs1v15/sha256.sig"),
+        ),
+        (
+            Hash::Sha384,
+            include_bytes!("../fixtures/pkcs1v15/sha384.sig"),
+        ),
+        (
+            Hash::Sha512,
+            include_bytes!("../fixtures/pkcs1v15/sha512.sig"),
+        ),
+    ];
+    for (hash, signature) in fixtures.iter() {
+        verify(&public_key, *hash, msg, signature).unwrap();
+    }
+}
+
+#[test]
+fn lenient_encodings_test() {
+    // Blocks a parsing verifier might accept: each carries the right digest
+    // but is not the one encoding EMSA-PKCS1-v1_5 allows.
+    let private_key = crate::test_keys::rsa1024();
+    let public_key = private_key.to_public_key();
+    let k = public_key.size();
+    let msg = b"forged";
+    let expected = emsa_encode(Hash::Sha256, msg, k).unwrap();
+    let digest_info_len = Hash::Sha256.digest_info_prefix().len() + 32;
+    let ps_end = k - digest_info_len - 1;
+
+    // Digest moved forward with garbage behind it, as in Bleichenbacher's 2006 forgery.
+    let mut garbage = vec![0, 1];
+    garbage.extend(vec![0xff; 8]);
+    garbage.push(0);
+    garbage.extend_from_slice(&expected[ps_end + 1..]);
+    garbage.resize(k, 0x42);
+    // DigestInfo without the NULL parameters.
+    let digest_info = &expected[ps_end + 1..];
+    let mut no_null = expected[..ps_end].to_vec();
+    no_null.extend_from_slice(&[0xff, 0xff, 0, 0x30, 0x2f, 0x30, 0x0b]);
+    no_null.extend_from_slice(&digest_info[4..15]);
+    no_null.extend_from_slice(&digest_info[17..]);
+    // Block type 2 instead of 1.
+    let mut block_type = expected.clone();
+    block_type[1] = 2;
+
+    for em in [garbage, no_null, block_type].iter() {
+        assert_eq!(em.len(), k);
+        let signature = i2osp(&private_key.sign_raw(&os2ip(em)).unwrap(), k);
+        assert!(matches!(
+            verify(&public_key, Hash::Sha256, msg, &signature),
+            Err(Error::InvalidSignature)
+        ));
+    }
+}
@@ -1,5 +1,8 @@
 use num_bigint::{BigInt, BigUint, RandBigInt, ToBigInt, ToBigUint};
-use num_traits::{one, zero};
+use num_traits::{one, zero, ToPrimitive};
+
+use crate::error::{Error, Result};
+use crate::montgomery::MontgomeryContext;
 
 fn new_gcd(a: &BigUint, b: &BigUint) -> BigUint {
     use std::mem::replace;
@@ -14,8 +17,8 @@ fn new_gcd(a: &BigUint, b: &BigUint) -> BigUint {
     b.clone()
 }
 
-fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
-    return new_gcd(a, b);
+pub fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
+    new_gcd(a, b)
 }
 
 #[test]
@@ -60,39 +63,169 @@ fn gcdext(a: BigInt, b: BigInt) -> (BigInt, BigInt, BigInt) {
     }
     let (d, x, y) = gcdext(b.clone(), &a % &b);
     let (x, y) = (y.clone(), x - (&a / &b) * y);
-    return (d, x, y);
+    (d, x, y)
 }
 
-pub fn mul_inv_mod(n: BigUint, m: BigUint) -> BigUint {
-    if gcd(&n, &m) != one() || n >= m {
-        //TODO: fix that n=3, m=5
-        panic!(
-            "Error in mul_inv_mod: n,m must be coprime and n must be less than m; n={:?},m={:?}",
-            n, m
-        );
+/// Inverse of `n` modulo `m`; fails with `Error::NotInvertible` unless `n` and `m` are coprime.
+pub fn mul_inv_mod(n: BigUint, m: BigUint) -> Result<BigUint> {
+    if m <= one() {
+        return Err(Error::NotInvertible);
+    }
+    let n = n % &m;
+    if gcd(&n, &m) != one() {
+        return Err(Error::NotInvertible);
     }
     let (_, _, inv) = gcdext(m.to_bigint().unwrap(), n.to_bigint().unwrap());
     if inv < zero() {
-        return (inv + m.to_bigint().unwrap()).to_biguint().unwrap();
+        Ok((inv + m.to_bigint().unwrap()).to_biguint().unwrap())
     } else {
-        return inv.to_biguint().unwrap();
+        Ok(inv.to_biguint().unwrap())
     }
 }
 
-pub fn get_lower_and_coprime_with(n: BigUint) -> BigUint {
-    let mut rng = rand::thread_rng();
+#[test]
+fn mul_inv_mod_test() {
+    let inv = |n: u32, m: u32| mul_inv_mod(n.to_biguint().unwrap(), m.to_biguint().unwrap());
+    assert_eq!(inv(3, 5).unwrap(), 2.to_biguint().unwrap());
+    assert_eq!(inv(8, 5).unwrap(), 2.to_biguint().unwrap());
+    assert_eq!(inv(17, 3120).unwrap(), 2753.to_biguint().unwrap());
+    assert!(matches!(inv(6, 9), Err(Error::NotInvertible)));
+    assert!(matches!(inv(1, 1), Err(Error::NotInvertible)));
+}
+
+/// Random `t` in `[2, n)` with `gcd(n, t) = 1`. Fails with
+/// `Error::InvalidArgument` if `n <= 2`, as the range is then empty.
+pub fn get_lower_and_coprime_with(n: BigUint) -> Result<BigUint> {
     let lower = 2.to_biguint().unwrap();
+    if n <= lower {
+        return Err(Error::InvalidArgument {
+            name: "n",
+            value: n.to_string(),
+        });
+    }
+    let mut rng = rand::thread_rng();
     let mut t: BigUint = rng.gen_biguint_range(&lower, &n);
     while gcd(&n, &t) != one() {
         t = rng.gen_biguint_range(&lower, &n);
     }
-    return t;
+    Ok(t)
+}
+
+#[test]
+fn get_lower_and_coprime_with_test() {
+    for &n in [3u32, 4, 10, 3120].iter() {
+        let t = get_lower_and_coprime_with(n.into()).unwrap();
+        assert!(t >= 2u32.into() && t < n.into());
+        assert_eq!(gcd(&t, &n.into()), one());
+    }
+    for &n in [0u32, 1, 2].iter() {
+        assert!(matches!(
+            get_lower_and_coprime_with(n.into()),
+            Err(Error::InvalidArgument { name: "n", .. })
+        ));
+    }
+}
+
+/// Amount of odd primes the candidate sieve divides by.
+const SIEVE_PRIMES_AMOUNT: usize = 2048;
+
+/// The first `SIEVE_PRIMES_AMOUNT` odd primes.
+fn sieve_primes() -> &'static [u32] {
+    use std::sync::OnceLock;
+    static PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut primes: Vec<u32> = Vec::with_capacity(SIEVE_PRIMES_AMOUNT);
+        let mut candidate = 3;
+        while primes.len() < SIEVE_PRIMES_AMOUNT {
+            if primes
+                .iter()
+                .take_while(|&&p| p * p <= candidate)
+                .all(|&p| candidate % p != 0)
+            {
+                primes.push(candidate);
+            }
+            candidate += 2;
+        }
+        primes
+    })
+}
+
+/// Walks the odd numbers `start, start + 2, ...`, keeping `start mod p` for
+/// every sieve prime so that candidates with a small factor are skipped
+/// without any big-number arithmetic.
+struct CandidateSieve {
+    start: BigUint,
+    residues: Vec<u32>,
+    delta: u64,
 }
 
-pub fn get_primes(n: usize, threads_amount: usize, bit_size: u64) -> Vec<BigUint> {
+impl CandidateSieve {
+    /// `start` must be odd. Below the largest sieve prime nothing is sieved,
+    /// since a candidate could be one of the sieve primes itself.
+    fn new(start: BigUint) -> Self {
+        let primes = sieve_primes();
+        let residues = if start > primes[primes.len() - 1].to_biguint().unwrap() {
+            primes
+                .iter()
+                .map(|&p| (&start % p).to_u32().unwrap())
+                .collect()
+        } else {
+            vec![]
+        };
+        CandidateSieve {
+            start,
+            residues,
+            delta: 0,
+        }
+    }
+
+    /// Next odd number with no factor among the sieve primes.
+    fn next_candidate(&mut self) -> BigUint {
+        loop {
+            let delta = self.delta;
+            self.delta += 2;
+            let has_small_factor = self
+                .residues
+                .iter()
+                .zip(sieve_primes())
+                .any(|(&r, &p)| (r as u64 + delta).is_multiple_of(p as u64));
+            if !has_small_factor {
+                return &self.start + delta;
+            }
+        }
+    }
+}
+
+/// Random odd `bit_size`-bit number with the top two bits set, so that the
+/// product of two such numbers has exactly `2 * bit_size` bits.
+fn random_start(rng: &mut impl RandBigInt, bit_size: u64) -> BigUint {
+    let mut num = rng.gen_biguint(bit_size);
+    num.set_bit(bit_size - 1, true);
+    num.set_bit(bit_size - 2, true);
+    num.set_bit(0, true);
+    num
+}
+
+/// Returns `n` distinct primes of exactly `bit_size` bits with the top two
+/// bits set. Fails with `Error::InvalidArgument` if `threads_amount` is 0 or
+/// `bit_size` is below 2.
+pub fn get_primes(n: usize, threads_amount: usize, bit_size: u64) -> Result<Vec<BigUint>> {
     use mpsc::TryRecvError::{Disconnected, Empty};
     use std::{collections::HashSet, sync::mpsc, thread};
 
+    if threads_amount == 0 {
+        return Err(Error::InvalidArgument {
+            name: "threads_amount",
+            value: threads_amount.to_string(),
+        });
+    }
+    if bit_size < 2 {
+        return Err(Error::InvalidArgument {
+            name: "bit_size",
+            value: bit_size.to_string(),
+        });
+    }
+
     let (primes_sender, primes_receiver) = mpsc::channel();
     let mut handles = Vec::with_capacity(threads_amount);
 
@@ -103,12 +236,7 @@ pub fn get_primes(n: usize, threads_amount: usize, bit_size: u64) -> Vec<BigUint
         let handle = thread::spawn(move || {
             let mut rng = rand::thread_rng();
 
-            let mut num = rng.gen_biguint(bit_size);
-            let step = 2.to_biguint().unwrap();
-
-            if &num % 2.to_biguint().unwrap() == zero() {
-                num += one::<BigUint>();
-            }
+            let mut candidates = CandidateSieve::new(random_start(&mut rng, bit_size));
 
             loop {
                 match should_stop.try_recv() {
@@ -119,19 +247,22 @@ pub fn get_primes(n: usize, threads_amount: usize, bit_size: u64) -> Vec<BigUint
                     },
                 };
 
-                if fast_prime_test(num.clone()) {
-                    if primes_sender.send(num.clone()).is_err() {
-                        return;
-                    }
+                let num = candidates.next_candidate();
+                if num.bits() > bit_size {
+                    candidates = CandidateSieve::new(random_start(&mut rng, bit_size));
+                    continue;
+                }
+                if fast_prime_test(num.clone()) && primes_sender.send(num).is_err() {
+                    return;
                 }
-
-                num += &step;
             }
         });
 
         handles.push((handle, say_stop));
     }
 
+    // Only the workers hold senders now, so the loop ends if they all die.
+    drop(primes_sender);
     let mut primes = HashSet::new();
     for received in primes_receiver {
         if hard_prime_test(received.clone()) {
@@ -150,69 +281,472 @@ pub fn get_primes(n: usize, threads_amount: usize, bit_size: u64) -> Vec<BigUint
         handler.join().unwrap();
     }
 
-    return primes.into_iter().collect();
+    Ok(primes.into_iter().collect())
 }
 
-pub fn powmod(b: BigUint, e: BigUint, m: BigUint) -> BigUint {
-    fn f(b: &BigUint, e: &BigUint, m: &BigUint, r: Option<&BigUint>) -> BigUint {
-        let __used_to_make_lifitime;
-        let r = match r {
-            Some(x) => x,
-            None => {
-                __used_to_make_lifitime = one();
-                &__used_to_make_lifitime
-            }
-        };
-        if *e == zero() {
-            return r.clone();
-        }
-        if e & one::<BigUint>() == one() {
-            return f(&((b * b) % m), &(e >> 1), m, Some(&((r * b) % m)));
-        } else {
-            return f(&((b * b) % m), &(e >> 1), m, Some(r));
-        }
+/// `b^e mod m`. Odd moduli go through a `MontgomeryContext`; callers doing
+/// many exponentiations with one modulus should keep a context themselves.
+pub fn powmod(b: &BigUint, e: &BigUint, m: &BigUint) -> BigUint {
+    match MontgomeryContext::new(m) {
+        Ok(context) => context.pow(b, e),
+        Err(_) => b.modpow(e, m),
     }
-    return f(&b, &e, &m, None);
 }
 
 type Number = BigUint;
 
-pub fn fast_prime_test(n: Number) -> bool {
-    use rand::prelude::*;
+/// Primes used for trial division before the Miller–Rabin rounds.
+const SMALL_PRIMES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
 
-    if n == one() || n == zero() {
-        return false;
+/// Trial division by `SMALL_PRIMES`. `Some(result)` if that settles primality of `n`.
+fn small_prime_test(n: &Number) -> Option<bool> {
+    if *n < 2u32.to_biguint().unwrap() {
+        return Some(false);
+    }
+    for &p in SMALL_PRIMES.iter() {
+        if *n == p.to_biguint().unwrap() {
+            return Some(true);
+        }
+        if n % p == zero() {
+            return Some(false);
+        }
     }
+    // A composite below 41^2 has a factor of at most 37.
+    if *n < (41u32 * 41).to_biguint().unwrap() {
+        return Some(true);
+    }
+    None
+}
 
-    let mut rng = rand::thread_rng();
-    for _ in 0..16 {
-        let a: Number = rng.gen_range(one()..n.clone());
-        if gcd(&a, &n) != one() {
+/// Number of Miller–Rabin rounds for a random `bits`-bit candidate, after
+/// FIPS 186-5 Table C.1 (error probability below 2^-100). Sizes below the
+/// table fall back to the worst-case bound of 4^-k.
+fn miller_rabin_rounds(bits: u64) -> usize {
+    match bits {
+        b if b >= 1536 => 4,
+        b if b >= 1024 => 5,
+        b if b >= 512 => 7,
+        _ => 50,
+    }
+}
+
+/// Strong probable prime test of odd `n` to base `a`, where `n - 1 = d * 2^s`
+/// and `context` is the Montgomery context for `n`.
+fn strong_probable_prime(context: &MontgomeryContext, a: &Number, d: &Number, s: u64) -> bool {
+    let n = context.modulus();
+    let n1 = n - one::<BigUint>();
+    let mut x = context.pow(a, d);
+    if x == one() || x == n1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = (&x * &x) % n;
+        if x == n1 {
+            return true;
+        }
+        if x == one() {
             return false;
         }
-        if powmod(a.clone(), &n - one::<BigUint>(), n.clone()) != one() {
+    }
+    false
+}
+
+/// Miller–Rabin test with `rounds` random bases in `[2, n-2]`.
+fn miller_rabin(n: &Number, rounds: usize) -> bool {
+    if let Some(result) = small_prime_test(n) {
+        return result;
+    }
+    let n1 = n - one::<BigUint>();
+    let s = n1.trailing_zeros().unwrap();
+    let d = &n1 >> s;
+    let context = MontgomeryContext::new(n).unwrap();
+
+    let mut rng = rand::thread_rng();
+    let two = 2.to_biguint().unwrap();
+    for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&two, &n1);
+        if !strong_probable_prime(&context, &a, &d, s) {
             return false;
         }
     }
     true
 }
 
+/// Miller–Rabin with the round count for a random candidate of this size.
+pub fn fast_prime_test(n: Number) -> bool {
+    miller_rabin(&n, miller_rabin_rounds(n.bits()))
+}
+
+/// Miller–Rabin with 64 rounds, enough (error below 2^-128) even for adversarially chosen `n`.
 pub fn hard_prime_test(n: Number) -> bool {
-    use rand::prelude::*;
+    miller_rabin(&n, 64)
+}
+
+/// Jacobi symbol `(a/n)` for odd `n`.
+fn jacobi(a: &Number, n: &Number) -> i32 {
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut result = 1;
+    while a != zero() {
+        let twos = a.trailing_zeros().unwrap();
+        a >>= twos;
+        let n_mod_8 = (&n % 8u32).to_u32().unwrap();
+        if twos % 2 == 1 && (n_mod_8 == 3 || n_mod_8 == 5) {
+            result = -result;
+        }
+        let a_mod_4 = (&a % 4u32).to_u32().unwrap();
+        if a_mod_4 == 3 && n_mod_8 % 4 == 3 {
+            result = -result;
+        }
+        std::mem::swap(&mut a, &mut n);
+        a %= &n;
+    }
+    if n == one() {
+        result
+    } else {
+        0
+    }
+}
 
-    if n == one() || n == zero() {
+/// `x` reduced modulo `n`, for a possibly negative `x`.
+fn signed_mod(x: i64, n: &Number) -> Number {
+    let abs = x.unsigned_abs().to_biguint().unwrap() % n;
+    if x < 0 && abs != zero() {
+        n - abs
+    } else {
+        abs
+    }
+}
+
+/// `x / 2 mod n` for odd `n`.
+fn half_mod(x: Number, n: &Number) -> Number {
+    if x.bit(0) {
+        (x + n) >> 1
+    } else {
+        x >> 1
+    }
+}
+
+/// Strong Lucas probable prime test of odd `n` with parameters `P = 1`,
+/// `Q = (1 - D) / 4`, where `D` is the first of 5, -7, 9, -11, ... with
+/// Jacobi symbol `(D/n) = -1` (Selfridge's method A).
+fn strong_lucas_test(n: &Number) -> bool {
+    let sqrt = n.sqrt();
+    if &sqrt * &sqrt == *n {
         return false;
     }
+    let mut d: i64 = 5;
+    loop {
+        match jacobi(&signed_mod(d, n), n) {
+            -1 => break,
+            0 if d.unsigned_abs().to_biguint().unwrap() != *n => return false,
+            _ => {}
+        }
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+    }
+    let d_mod = signed_mod(d, n);
+    let q_mod = signed_mod((1 - d) / 4, n);
 
-    let mut rng = rand::thread_rng();
-    for _ in 0..128 {
-        let a: Number = rng.gen_range(one()..n.clone());
-        if gcd(&a, &n) != one() {
-            return false;
+    let n1 = n + one::<BigUint>();
+    let s = n1.trailing_zeros().unwrap();
+    let k = &n1 >> s;
+
+    // U_1 = 1, V_1 = P = 1, Q^1 = Q; walk the bits of k from the top.
+    let mut u = one::<BigUint>();
+    let mut v = one::<BigUint>();
+    let mut q_k = q_mod.clone();
+    for i in (0..k.bits() - 1).rev() {
+        u = (&u * &v) % n;
+        v = (&v * &v + (n - &q_k) * 2u32) % n;
+        q_k = (&q_k * &q_k) % n;
+        if k.bit(i) {
+            let next_u = half_mod(&u + &v, n);
+            v = half_mod(&d_mod * &u + &v, n) % n;
+            u = next_u % n;
+            q_k = (&q_k * &q_mod) % n;
         }
-        if powmod(a.clone(), &n - one::<BigUint>(), n.clone()) != one() {
-            return false;
+    }
+    if u == zero() || v == zero() {
+        return true;
+    }
+    for _ in 1..s {
+        v = (&v * &v + (n - &q_k) * 2u32) % n;
+        if v == zero() {
+            return true;
         }
+        q_k = (&q_k * &q_k) % n;
     }
-    true
+    false
+}
+
+/// Baillie–PSW test: a strong probable prime test to base 2 followed by a
+/// strong Lucas test. Deterministic, and correct for every `n < 2^64`.
+pub fn is_probable_prime_bpsw(n: Number) -> bool {
+    if let Some(result) = small_prime_test(&n) {
+        return result;
+    }
+    let n1 = &n - one::<BigUint>();
+    let s = n1.trailing_zeros().unwrap();
+    let d = &n1 >> s;
+    let context = MontgomeryContext::new(&n).unwrap();
+    strong_probable_prime(&context, &2.to_biguint().unwrap(), &d, s) && strong_lucas_test(&n)
+}
+
+#[cfg(test)]
+fn sieve(limit: usize) -> Vec<bool> {
+    let mut is_prime = vec![true; limit];
+    is_prime[0] = false;
+    is_prime[1] = false;
+    for i in 2..limit {
+        if is_prime[i] {
+            for j in (i * i..limit).step_by(i) {
+                is_prime[j] = false;
+            }
+        }
+    }
+    is_prime
+}
+
+#[test]
+fn get_primes_test() {
+    for &bit_size in [16u64, 64, 127].iter() {
+        for p in get_primes(4, 2, bit_size).unwrap() {
+            assert_eq!(p.bits(), bit_size);
+            assert!(p.bit(bit_size - 2));
+            assert!(is_probable_prime_bpsw(p));
+        }
+    }
+    assert!(matches!(
+        get_primes(2, 0, 16),
+        Err(Error::InvalidArgument {
+            name: "threads_amount",
+            ..
+        })
+    ));
+    for &bit_size in [0u64, 1].iter() {
+        assert!(matches!(
+            get_primes(2, 2, bit_size),
+            Err(Error::InvalidArgument {
+                name: "bit_size",
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn candidate_sieve_test() {
+    let primes = sieve_primes();
+    assert_eq!(primes.len(), SIEVE_PRIMES_AMOUNT);
+    assert_eq!(&primes[..5], &[3, 5, 7, 11, 13]);
+
+    let start = (one::<BigUint>() << 64u32) + 1u32;
+    let mut candidates = CandidateSieve::new(start.clone());
+    let mut candidate = candidates.next_candidate();
+    let mut num = start;
+    while num < (one::<BigUint>() << 64u32) + 100_000u32 {
+        if num == candidate {
+            assert!(primes.iter().all(|&p| &num % p != zero()), "n={}", num);
+            candidate = candidates.next_candidate();
+        } else {
+            assert!(
+                !is_probable_prime_bpsw(num.clone()),
+                "skipped prime {}",
+                num
+            );
+        }
+        num += 2u32;
+    }
+
+    let mut candidates = CandidateSieve::new(3.to_biguint().unwrap());
+    let first: Vec<BigUint> = (0..4).map(|_| candidates.next_candidate()).collect();
+    assert_eq!(
+        first,
+        [3u32, 5, 7, 9]
+            .iter()
+            .map(|&x| x.into())
+            .collect::<Vec<BigUint>>()
+    );
+}
+
+#[test]
+fn powmod_test() {
+    let (b, m) = (4.to_biguint().unwrap(), 497.to_biguint().unwrap());
+    assert_eq!(
+        powmod(&b, &13.to_biguint().unwrap(), &m),
+        445.to_biguint().unwrap()
+    );
+    // Even moduli fall back to plain modular exponentiation.
+    let m = 496.to_biguint().unwrap();
+    assert_eq!(
+        powmod(&b, &13.to_biguint().unwrap(), &m),
+        b.modpow(&13u32.into(), &m)
+    );
+    assert_eq!(powmod(&b, &13.to_biguint().unwrap(), &one()), zero());
+}
+
+#[test]
+fn small_numbers_test() {
+    for (n, &expected) in sieve(10_000).iter().enumerate() {
+        let n = n.to_biguint().unwrap();
+        assert_eq!(fast_prime_test(n.clone()), expected, "n={}", n);
+        assert_eq!(hard_prime_test(n.clone()), expected, "n={}", n);
+    }
+}
+
+#[test]
+fn large_primes_test() {
+    for exp in [61u32, 89, 107, 127, 521].iter() {
+        let mersenne = (one::<BigUint>() << *exp) - one::<BigUint>();
+        assert!(fast_prime_test(mersenne.clone()), "2^{}-1", exp);
+        assert!(hard_prime_test(mersenne), "2^{}-1", exp);
+    }
+    let composite = (one::<BigUint>() << 67u32) - one::<BigUint>();
+    assert!(!fast_prime_test(composite));
+}
+
+#[test]
+fn carmichael_numbers_test() {
+    let carmichael: [u64; 12] = [
+        561,
+        1105,
+        1729,
+        2465,
+        2821,
+        6601,
+        8911,
+        10585,
+        15841,
+        29341,
+        41041,
+        9_999_109_081,
+    ];
+    for &n in carmichael.iter() {
+        let n = n.to_biguint().unwrap();
+        assert!(!fast_prime_test(n.clone()), "n={}", n);
+        assert!(!hard_prime_test(n.clone()), "n={}", n);
+    }
+}
+
+#[test]
+fn large_carmichael_numbers_test() {
+    // Chernick's form: (6k+1)(12k+1)(18k+1) is a Carmichael number whenever all
+    // three factors are prime, so a Fermat test passes for every coprime base.
+    let mut found = 0;
+    let mut k = 1_000_000_000_000u64.to_biguint().unwrap();
+    while found < 3 {
+        k += one::<BigUint>();
+        let factors = [&k * 6u32 + 1u32, &k * 12u32 + 1u32, &k * 18u32 + 1u32];
+        if !factors.iter().all(|f| hard_prime_test(f.clone())) {
+            continue;
+        }
+        let n: BigUint = factors.iter().product();
+        let n1 = &n - one::<BigUint>();
+        assert_eq!(powmod(&2.to_biguint().unwrap(), &n1, &n), one());
+        assert!(!fast_prime_test(n.clone()), "n={}", n);
+        assert!(!hard_prime_test(n), "k={}", k);
+        found += 1;
+    }
+}
+
+#[test]
+fn strong_pseudoprimes_test() {
+    // Strong pseudoprimes to base 2, to bases 2 and 3, to bases 2..5, to
+    // bases 2..7, and to all prime bases up to 23.
+    let pseudoprimes: [u64; 9] = [
+        2047,
+        3277,
+        4033,
+        4681,
+        8321,
+        1_373_653,
+        25_326_001,
+        3_215_031_751,
+        3_825_123_056_546_413_051,
+    ];
+    for &n in pseudoprimes.iter() {
+        let n = n.to_biguint().unwrap();
+        let n1 = &n - one::<BigUint>();
+        let s = n1.trailing_zeros().unwrap();
+        let d = &n1 >> s;
+        let context = MontgomeryContext::new(&n).unwrap();
+        assert!(strong_probable_prime(
+            &context,
+            &2.to_biguint().unwrap(),
+            &d,
+            s
+        ));
+        assert!(!fast_prime_test(n.clone()), "n={}", n);
+        assert!(!hard_prime_test(n.clone()), "n={}", n);
+    }
+}
+
+#[test]
+fn jacobi_test() {
+    // (a/n) for n = 45 and a = 0..9, from the table of Jacobi symbols.
+    let expected = [0, 1, -1, 0, 1, 0, 0, -1, -1, 0];
+    for (a, &symbol) in expected.iter().enumerate() {
+        let a = a.to_biguint().unwrap();
+        assert_eq!(jacobi(&a, &45.to_biguint().unwrap()), symbol, "a={}", a);
+    }
+}
+
+#[test]
+fn bpsw_small_numbers_test() {
+    for (n, &expected) in sieve(10_000).iter().enumerate() {
+        let n = n.to_biguint().unwrap();
+        assert_eq!(is_probable_prime_bpsw(n.clone()), expected, "n={}", n);
+    }
+}
+
+#[test]
+fn bpsw_pseudoprimes_test() {
+    let strong_pseudoprimes: [u64; 5] = [
+        1_373_653,
+        25_326_001,
+        3_215_031_751,
+        2_152_302_898_747,
+        3_825_123_056_546_413_051,
+    ];
+    for &n in strong_pseudoprimes.iter() {
+        assert!(!is_probable_prime_bpsw(n.to_biguint().unwrap()), "n={}", n);
+    }
+    // Strong Lucas pseudoprimes with Selfridge parameters; base 2 catches them.
+    let lucas_pseudoprimes: [u64; 7] = [5459, 5777, 10877, 16109, 18971, 22499, 24569];
+    for &n in lucas_pseudoprimes.iter() {
+        let n = n.to_biguint().unwrap();
+        assert!(strong_lucas_test(&n), "n={}", n);
+        assert!(!is_probable_prime_bpsw(n.clone()), "n={}", n);
+    }
+    for exp in [61u32, 89, 107, 127, 521].iter() {
+        let mersenne = (one::<BigUint>() << *exp) - one::<BigUint>();
+        assert!(is_probable_prime_bpsw(mersenne), "2^{}-1", exp);
+    }
+}
+
+#[test]
+fn bpsw_u64_test() {
+    // Miller–Rabin with the first 12 prime bases is exact below 3.3 * 10^24.
+    fn deterministic_miller_rabin(n: &Number) -> bool {
+        if let Some(result) = small_prime_test(n) {
+            return result;
+        }
+        let n1 = n - one::<BigUint>();
+        let s = n1.trailing_zeros().unwrap();
+        let d = &n1 >> s;
+        let context = MontgomeryContext::new(n).unwrap();
+        SMALL_PRIMES
+            .iter()
+            .all(|&a| strong_probable_prime(&context, &a.to_biguint().unwrap(), &d, s))
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut primes = 0;
+    for _ in 0..20_000 {
+        let n = rng.gen_biguint(64) | one::<BigUint>();
+        let expected = deterministic_miller_rabin(&n);
+        assert_eq!(is_probable_prime_bpsw(n.clone()), expected, "n={}", n);
+        primes += expected as usize;
+    }
+    assert!(primes > 0);
 }
@@ -0,0 +1,185 @@
+//! RSASSA-PSS (RFC 8017 §8.1) with MGF1 over the message hash.
+
+use rand::RngCore;
+use subtle::ConstantTimeEq;
+
+use crate::cipher::{i2osp, os2ip};
+use crate::error::{Error, Result};
+use crate::hash::{mgf1, Hash};
+use crate::keys::{RsaPrivateKey, RsaPublicKey};
+
+/// `M' = 0x00 00 00 00 00 00 00 00 || mHash || salt`, hashed.
+fn salted_hash(hash: Hash, m_hash: &[u8], salt: &[u8]) -> Vec<u8> {
+    let mut m_prime = vec![0; 8];
+    m_prime.extend_from_slice(m_hash);
+    m_prime.extend_from_slice(salt);
+    hash.digest(&m_prime)
+}
+
+/// EMSA-PSS-ENCODE of `msg` into `emBits = modBits - 1` bits.
+fn encode(hash: Hash, salt_len: usize, msg: &[u8], em_bits: usize) -> Result<Vec<u8>> {
+    let h_len = hash.output_len();
+    let em_len = em_bits.div_ceil(8);
+    if em_len < h_len + salt_len + 2 {
+        return Err(Error::MessageTooLong);
+    }
+    let mut salt = vec![0; salt_len];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let h = salted_hash(hash, &hash.digest(msg), &salt);
+
+    // DB = PS || 0x01 || salt
+    let mut db = vec![0; em_len - salt_len - h_len - 2];
+    db.push(1);
+    db.extend(salt);
+    for (byte, mask) in db.iter_mut().zip(mgf1(hash, &h, em_len - h_len - 1)) {
+        *byte ^= mask;
+    }
+    db[0] &= 0xff >> (8 * em_len - em_bits);
+
+    let mut em = db;
+    em.extend(h);
+    em.push(0xbc);
+    Ok(em)
+}
+
+/// EMSA-PSS-VERIFY; `em` is `emLen` bytes long.
+fn verify_encoding(hash: Hash, salt_len: usize, msg: &[u8], em: &[u8], em_bits: usize) -> bool {
+    let h_len = hash.output_len();
+    let em_len = em.len();
+    if em_len < h_len + salt_len + 2 || em[em_len - 1] != 0xbc {
+        return false;
+    }
+    let (masked_db, h) = em[..em_len - 1].split_at(em_len - h_len - 1);
+    let top_mask = !(0xffu8 >> (8 * em_len - em_bits));
+    if masked_db[0] & top_mask != 0 {
+        return false;
+    }
+    let mut db = masked_db.to_vec();
+    for (byte, mask) in db.iter_mut().zip(mgf1(hash, h, masked_db.len())) {
+        *byte ^= mask;
+    }
+    db[0] &= !top_mask;
+
+    let ps_len = em_len - h_len - salt_len - 2;
+    if db[..ps_len].iter().any(|&byte| byte != 0) || db[ps_len] != 1 {
+        return false;
+    }
+    let salt = &db[ps_len + 1..];
+    bool::from(salted_hash(hash, &hash.digest(msg), salt).ct_eq(h))
+}
+
+/// RSASSA-PSS-SIGN with a random salt of `salt_len` bytes. Fails with
+/// `Error::MessageTooLong` if the key is too small for the hash and salt.
+pub fn sign(
+    private_key: &RsaPrivateKey,
+    hash: Hash,
+    salt_len: usize,
+    msg: &[u8],
+) -> Result<Vec<u8>> {
+    let em_bits = private_key.n().bits() as usize - 1;
+    let em = encode(hash, salt_len, msg, em_bits)?;
+    let s = private_key.sign_raw(&os2ip(&em))?;
+    Ok(i2osp(&s, private_key.size()))
+}
+
+/// RSASSA-PSS-VERIFY. Fails with `Error::InvalidSignature` unless `signature`
+/// is a signature of `msg` made with the same hash and salt length.
+pub fn verify(
+    public_key: &RsaPublicKey,
+    hash: Hash,
+    salt_len: usize,
+    msg: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    if signature.len() != public_key.size() {
+        return Err(Error::InvalidSignature);
+    }
+    let m = public_key
+        .encrypt_raw(&os2ip(signature))
+        .map_err(|_| Error::InvalidSignature)?;
+    let em_bits = public_key.n().bits() as usize - 1;
+    let em_len = em_bits.div_ceil(8);
+    if m.bits() as usize > em_bits {
+        return Err(Error::InvalidSignature);
+    }
+    if !verify_encoding(hash, salt_len, msg, &i2osp(&m, em_len), em_bits) {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(())
+}
+
+#[test]
+fn sign_verify_test() {
+    let private_key = crate::test_keys::rsa2048();
+    let public_key = private_key.to_public_key();
+    let msg = b"Hello, world!";
+    for &hash in [Hash::Sha1, Hash::Sha256, Hash::Sha384, Hash::Sha512].iter() {
+        for &salt_len in [0, hash.output_len()].iter() {
+            let signature = sign(&private_key, hash, salt_len, msg).unwrap();
+            assert_eq!(signature.len(), public_key.size());
+            verify(&public_key, hash, salt_len, msg, &signature).unwrap();
+            assert!(matches!(
+                verify(&public_key, hash, salt_len, b"Hello, world?", &signature),
+                Err(Error::InvalidSignature)
+            ));
+        }
+    }
+    let signature = sign(&private_key, Hash::Sha256, 32, msg).unwrap();
+    assert!(matches!(
+        verify(&public_key, Hash::Sha256, 20, msg, &signature),
+        Err(Error::InvalidSignature)
+    ));
+    assert!(matches!(
+        verify(&public_key, Hash::Sha384, 32, msg, &signature),
+        Err(Error::InvalidSignature)
+    ));
+    let mut corrupted = signature.clone();
+    corrupted[10] ^= 1;
+    assert!(matches!(
+        verify(&public_key, Hash::Sha256, 32, msg, &corrupted),
+        Err(Error::InvalidSignature)
+    ));
+    assert!(matches!(
+        verify(&public_key, Hash::Sha256, 32, msg, &signature[1..]),
+        Err(Error::InvalidSignature)
+    ));
+}
+
+#[test]
+fn small_key_test() {
+    // 1024-bit modulus: emLen = 128 fits SHA-512 with a 62-byte salt at most.
+    let private_key = crate::test_keys::rsa1024();
+    let public_key = private_key.to_public_key();
+    let signature = sign(&private_key, Hash::Sha512, 62, b"x").unwrap();
+    verify(&public_key, Hash::Sha512, 62, b"x", &signature).unwrap();
+    assert!(matches!(
+        sign(&private_key, Hash::Sha512, 63, b"x"),
+        Err(Error::MessageTooLong)
+    ));
+}
+
+#[test]
+fn openssl_fixtures_test() {
+    let public_key = crate::test_keys::rsa2048().to_public_key();
+    let msg = include_bytes!("../fixtures/pss/message.txt");
+    let fixtures: [(Hash, usize, &[u8]); 3] = [
+        (
+            Hash::Sha256,
+            32,
+            include_bytes!("../fixtures/pss/sha256.sig"),
+        ),
+        (
+            Hash::Sha384,
+            0,
+            include_bytes!("../fixtures/pss/sha384_salt0.sig"),
+        ),
+        (
+            Hash::Sha512,
+            64,
+            include_bytes!("../fixtures/pss/sha512.sig"),
+        ),
+    ];
+    for (hash, salt_len, signature) in fixtures.iter() {
+        verify(&public_key, *hash, *salt_len, msg, signature).unwrap();
+    }
+}
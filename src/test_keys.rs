@@ -0,0 +1,12 @@
+//! Fixture keys shared by the unit tests; see `fixtures/README.md`.
+
+use crate::keys::{read_private_key, RsaPrivateKey};
+
+pub(crate) fn rsa1024() -> RsaPrivateKey {
+    read_private_key(include_bytes!("../fixtures/rsa1024_private_key.txt")).unwrap()
+}
+
+/// Wide enough for SHA-512 OAEP and PSS with a full-length salt.
+pub(crate) fn rsa2048() -> RsaPrivateKey {
+    read_private_key(include_bytes!("../fixtures/rsa2048_private_key.txt")).unwrap()
+}